@@ -0,0 +1,66 @@
+use crate::billing::{self, Budget};
+use crate::storage::{AccessStatus, Storage};
+
+/// Обработать команду администратора: `/usage`, `/ban`, `/unban`,
+/// `/allow`, `/disallow` и `/access`. `admin_id` задан, если отправитель
+/// администратор. Возвращает `None`, если текст не команда администратора.
+pub fn handle_command(
+    text: &str,
+    admin_id: Option<u64>,
+    storage: &Storage,
+    budget: &Budget,
+) -> Option<String> {
+    let mut parts = text.split_whitespace();
+    let command = parts.next()?;
+    if !matches!(
+        command,
+        "/usage" | "/ban" | "/unban" | "/allow" | "/disallow" | "/access"
+    ) {
+        return None;
+    }
+    let Some(admin_id) = admin_id else {
+        return Some("Команда доступна только администраторам.".to_string());
+    };
+
+    let reply = match command {
+        "/usage" => billing::usage_report(storage, budget),
+        "/access" => access_report(storage),
+        _ => {
+            let Some(user_id) = parts.next().and_then(|id| id.parse::<u64>().ok()) else {
+                return Some(format!("Использование: {command} <user_id>"));
+            };
+            let (status, done) = match command {
+                "/ban" => (Some(AccessStatus::Banned), "заблокирован"),
+                "/allow" => (Some(AccessStatus::Allowed), "добавлен в список доступа"),
+                _ => (None, "удален из списков доступа"),
+            };
+            log::info!("Admin {admin_id} ran {command} {user_id}");
+            storage
+                .set_access(user_id, status, admin_id)
+                .map(|_| format!("Пользователь {user_id} {done}."))
+        }
+    };
+    Some(reply.unwrap_or_else(|err| {
+        log::error!("Failed to run admin command {}: {}", command, err);
+        "Не удалось выполнить команду.".to_string()
+    }))
+}
+
+fn access_report(storage: &Storage) -> anyhow::Result<String> {
+    let list = storage.access_list()?;
+    if list.is_empty() {
+        return Ok("Списки доступа пусты.".to_string());
+    }
+    let ids = |wanted: AccessStatus| {
+        list.iter()
+            .filter(|(_, status)| *status == wanted)
+            .map(|(id, _)| id.to_string())
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    Ok(format!(
+        "Разрешены: {}\nЗаблокированы: {}",
+        ids(AccessStatus::Allowed),
+        ids(AccessStatus::Banned)
+    ))
+}
@@ -0,0 +1,265 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+
+use crate::llm_provider::TokenUsage;
+use crate::normalize;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CacheConfig {
+    /// Время жизни записи, `Duration::ZERO` отключает кэш
+    #[serde(rename = "ttl_secs", deserialize_with = "crate::config::secs")]
+    pub ttl: Duration,
+    pub max_entries: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        CacheConfig {
+            ttl: Duration::from_secs(6 * 60 * 60),
+            max_entries: 1000,
+        }
+    }
+}
+
+/// Закэшированный результат вызова LLM и сколько токенов он стоил
+#[derive(Debug, Clone, PartialEq)]
+pub struct Cached<T> {
+    pub value: T,
+    pub usage: TokenUsage,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct CacheKey {
+    question: String,
+    program: Option<String>,
+}
+
+#[derive(Debug)]
+struct Entry<T> {
+    cached: Cached<T>,
+    inserted: Instant,
+}
+
+#[derive(Debug)]
+struct TtlMap<T> {
+    entries: HashMap<CacheKey, Entry<T>>,
+}
+
+impl<T: Clone> TtlMap<T> {
+    fn new() -> Self {
+        TtlMap {
+            entries: HashMap::new(),
+        }
+    }
+
+    fn get(&mut self, key: &CacheKey, ttl: Duration) -> Option<Cached<T>> {
+        match self.entries.get(key) {
+            Some(entry) if entry.inserted.elapsed() < ttl => Some(entry.cached.clone()),
+            Some(_) => {
+                self.entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn put(&mut self, key: CacheKey, cached: Cached<T>, ttl: Duration, max_entries: usize) {
+        if self.entries.len() >= max_entries {
+            self.entries
+                .retain(|_, entry| entry.inserted.elapsed() < ttl);
+        }
+        if self.entries.len() >= max_entries
+            && let Some(oldest) = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.inserted)
+                .map(|(key, _)| key.clone())
+        {
+            self.entries.remove(&oldest);
+        }
+        self.entries.insert(
+            key,
+            Entry {
+                cached,
+                inserted: Instant::now(),
+            },
+        );
+    }
+}
+
+#[derive(Debug)]
+struct CacheState {
+    /// Версия шаблонов промптов, с которыми получены закэшированные ответы
+    prompt_version: Option<String>,
+    answers: TtlMap<String>,
+    fields: TtlMap<Vec<String>>,
+}
+
+/// Кэш итоговых ответов и выбора релевантных полей. Ключ - нормализованный
+/// вопрос и программа, записи живут `ttl` и сбрасываются целиком при смене
+/// шаблонов промптов. Данные программ загружаются один раз при старте,
+/// так что новые данные попадают в ответы после перезапуска.
+#[derive(Debug)]
+pub struct AnswerCache {
+    config: CacheConfig,
+    state: Mutex<CacheState>,
+}
+
+impl AnswerCache {
+    pub fn new(config: CacheConfig) -> Self {
+        AnswerCache {
+            config,
+            state: Mutex::new(CacheState {
+                prompt_version: None,
+                answers: TtlMap::new(),
+                fields: TtlMap::new(),
+            }),
+        }
+    }
+
+    fn enabled(&self) -> bool {
+        !self.config.ttl.is_zero() && self.config.max_entries > 0
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap()
+    }
+
+    /// Сбросить кэш, если шаблоны промптов поменялись
+    pub fn set_prompt_version(&self, version: &str) {
+        let mut state = self.state.lock().unwrap();
+        if state.prompt_version.as_deref() != Some(version) {
+            if state.prompt_version.is_some() {
+                log::info!("Prompt templates changed, clearing answer cache");
+            }
+            state.prompt_version = Some(version.to_string());
+            state.answers = TtlMap::new();
+            state.fields = TtlMap::new();
+        }
+    }
+
+    pub fn answer(&self, question: &str, program: Option<&str>) -> Option<Cached<String>> {
+        if !self.enabled() {
+            return None;
+        }
+        self.state()
+            .answers
+            .get(&key(question, program), self.config.ttl)
+    }
+
+    pub fn put_answer(&self, question: &str, program: Option<&str>, answer: Cached<String>) {
+        if !self.enabled() {
+            return;
+        }
+        self.state().answers.put(
+            key(question, program),
+            answer,
+            self.config.ttl,
+            self.config.max_entries,
+        );
+    }
+
+    pub fn fields(&self, question: &str, program: Option<&str>) -> Option<Cached<Vec<String>>> {
+        if !self.enabled() {
+            return None;
+        }
+        self.state()
+            .fields
+            .get(&key(question, program), self.config.ttl)
+    }
+
+    pub fn put_fields(&self, question: &str, program: Option<&str>, fields: Cached<Vec<String>>) {
+        if !self.enabled() {
+            return;
+        }
+        self.state().fields.put(
+            key(question, program),
+            fields,
+            self.config.ttl,
+            self.config.max_entries,
+        );
+    }
+}
+
+fn key(question: &str, program: Option<&str>) -> CacheKey {
+    CacheKey {
+        question: normalize_question(question),
+        program: program.map(str::to_string),
+    }
+}
+
+/// Привести вопрос к виду, в котором одинаковые по смыслу формулировки
+/// совпадают: основы слов без пунктуации и лишних пробелов, синонимы
+/// и сокращения в одной форме, см. `normalize::normalize`
+pub fn normalize_question(text: &str) -> String {
+    normalize::normalize(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(ttl: Duration) -> AnswerCache {
+        AnswerCache::new(CacheConfig {
+            ttl,
+            max_entries: 2,
+        })
+    }
+
+    fn answer(text: &str) -> Cached<String> {
+        Cached {
+            value: text.to_string(),
+            usage: TokenUsage::default(),
+        }
+    }
+
+    #[test]
+    fn test_normalize_question() {
+        assert_eq!(
+            normalize_question("  Сколько стоит   обучение?! Ещё"),
+            normalize_question("сколько стоит обучения еще")
+        );
+        assert_eq!(
+            normalize_question("Есть курсы по ML?"),
+            normalize_question("есть курс по машинному обучению")
+        );
+    }
+
+    #[test]
+    fn test_hits_by_normalized_question_and_program() {
+        let cache = cache(Duration::from_secs(60));
+        cache.put_answer("Стоимость обучения?", Some("ai"), answer("дорого"));
+
+        assert_eq!(
+            cache.answer("стоимость  обучения", Some("ai")),
+            Some(answer("дорого"))
+        );
+        assert_eq!(cache.answer("стоимость обучения", Some("ai_product")), None);
+
+        // Самая старая запись вытесняется при переполнении
+        cache.put_answer("q2", None, answer("a2"));
+        cache.put_answer("q3", None, answer("a3"));
+        assert_eq!(cache.answer("стоимость обучения", Some("ai")), None);
+        assert!(cache.answer("q3", None).is_some());
+    }
+
+    #[test]
+    fn test_expires_and_invalidates_on_prompt_change() {
+        let expired = cache(Duration::from_nanos(1));
+        expired.put_answer("q", None, answer("a"));
+        std::thread::sleep(Duration::from_millis(1));
+        assert_eq!(expired.answer("q", None), None);
+
+        let cache = cache(Duration::from_secs(60));
+        cache.set_prompt_version("1");
+        cache.put_answer("q", None, answer("a"));
+        cache.set_prompt_version("1");
+        assert!(cache.answer("q", None).is_some());
+        cache.set_prompt_version("2");
+        assert_eq!(cache.answer("q", None), None);
+    }
+}
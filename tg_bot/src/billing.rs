@@ -0,0 +1,251 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::sync::watch;
+
+use crate::embeddings::{Embedding, EmbeddingKind, EmbeddingProvider};
+use crate::llm_error::LlmError;
+use crate::llm_provider::{
+    ChatCompletion, ChatMessage, GenerationOptions, LlmProvider, TokenUsage,
+};
+use crate::storage::{Period, Storage};
+
+/// Цена модели в рублях за 1000 токенов
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    pub input_per_1k: f64,
+    pub output_per_1k: f64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PriceList {
+    prices: HashMap<String, ModelPrice>,
+}
+
+impl PriceList {
+    /// Разобрать строку вида `yandexgpt=1.2:1.2;gpt-4o-mini=0.015:0.06`,
+    /// где для каждой модели указаны цены входных и выходных токенов за 1000
+    pub fn parse(spec: &str) -> anyhow::Result<Self> {
+        let mut prices = HashMap::new();
+        for entry in spec.split(';').map(str::trim).filter(|e| !e.is_empty()) {
+            let (model, price) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow::anyhow!("Invalid price entry: {entry}"))?;
+            let (input, output) = price.split_once(':').unwrap_or((price, price));
+            prices.insert(
+                model.trim().to_string(),
+                ModelPrice {
+                    input_per_1k: input.trim().parse()?,
+                    output_per_1k: output.trim().parse()?,
+                },
+            );
+        }
+        Ok(PriceList { prices })
+    }
+
+    /// Стоимость вызова. Для модели без цены возвращает 0 и пишет предупреждение.
+    pub fn cost(&self, model: &str, usage: &TokenUsage) -> f64 {
+        match self.prices.get(model) {
+            Some(price) => {
+                (usage.input_tokens as f64 * price.input_per_1k
+                    + usage.completion_tokens as f64 * price.output_per_1k)
+                    / 1000.0
+            }
+            None => {
+                log::warn!("No price configured for model {model}");
+                0.0
+            }
+        }
+    }
+}
+
+/// Лимиты расходов в рублях. `None` означает отсутствие лимита.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Budget {
+    pub daily: Option<f64>,
+    pub monthly: Option<f64>,
+}
+
+impl Budget {
+    /// Какой лимит исчерпан, если исчерпан
+    pub fn exceeded(&self, storage: &Storage) -> anyhow::Result<Option<Period>> {
+        for (period, limit) in [
+            (Period::Today, self.daily),
+            (Period::ThisMonth, self.monthly),
+        ] {
+            if let Some(limit) = limit
+                && storage.spending(period)?.cost >= limit
+            {
+                return Ok(Some(period));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Отчет о расходах для команды администратора `/usage`
+pub fn usage_report(storage: &Storage, budget: &Budget) -> anyhow::Result<String> {
+    let limit = |limit: Option<f64>| match limit {
+        Some(limit) => format!("{limit:.2} ₽"),
+        None => "без лимита".to_string(),
+    };
+    let mut report = String::new();
+    for (title, period, budget_limit) in [
+        ("Сегодня", Period::Today, budget.daily),
+        ("С начала месяца", Period::ThisMonth, budget.monthly),
+    ] {
+        let spending = storage.spending(period)?;
+        report.push_str(&format!(
+            "{title}: {} вопросов, {} токенов (вход {}, выход {}), {:.2} ₽ из {}, из кэша {} вызовов ({} токенов)\n",
+            spending.questions,
+            spending.usage.total_tokens,
+            spending.usage.input_tokens,
+            spending.usage.completion_tokens,
+            spending.cost,
+            limit(budget_limit),
+            spending.cache_hits,
+            spending.saved_tokens,
+        ));
+    }
+    let week = storage.usage_summary(7)?;
+    report.push_str(&format!(
+        "За 7 дней: {} чатов, {} вопросов, {} ошибок, {} неразобранных ответов модели, {:.2} ₽, средняя задержка {:.0} мс",
+        week.chats,
+        week.messages,
+        week.errors,
+        week.parse_failures,
+        week.cost,
+        week.avg_latency_ms
+    ));
+    Ok(report)
+}
+
+/// Как долго `BudgetGuard` доверяет прочитанным из базы расходам
+const BUDGET_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Обертка над провайдером, которая перестает обращаться к LLM,
+/// когда дневной или месячный бюджет исчерпан. Бот в этом случае
+/// отвечает упрощенно, без модели. Векторы вопросов для семантического
+/// поиска тоже стоят денег, поэтому оборачивается и `EmbeddingProvider`.
+/// Расходы суммируются запросом к SQLite, поэтому результат проверки
+/// переиспользуется `BUDGET_CHECK_INTERVAL`, а не считается на каждый вызов.
+pub struct BudgetGuard<P: ?Sized = dyn LlmProvider> {
+    inner: Arc<P>,
+    budget: Budget,
+    storage: Arc<Storage>,
+    /// Когда последний раз проверяли лимиты и какой из них был исчерпан
+    checked: Mutex<Option<(Instant, Option<Period>)>>,
+}
+
+impl<P: ?Sized> BudgetGuard<P> {
+    pub fn new(inner: Arc<P>, budget: Budget, storage: Arc<Storage>) -> Self {
+        BudgetGuard {
+            inner,
+            budget,
+            storage,
+            checked: Mutex::new(None),
+        }
+    }
+
+    fn exceeded(&self) -> anyhow::Result<Option<Period>> {
+        let mut checked = self.checked.lock().unwrap();
+        if let Some((at, exceeded)) = *checked
+            && at.elapsed() < BUDGET_CHECK_INTERVAL
+        {
+            return Ok(exceeded);
+        }
+        let exceeded = self.budget.exceeded(&self.storage)?;
+        *checked = Some((Instant::now(), exceeded));
+        Ok(exceeded)
+    }
+
+    fn check(&self) -> Result<(), LlmError> {
+        match self.exceeded() {
+            Ok(Some(period)) => {
+                log::warn!("LLM budget exceeded for {:?}", period);
+                Err(LlmError::BudgetExceeded)
+            }
+            Ok(None) => Ok(()),
+            Err(err) => {
+                // Не блокируем ответы из-за ошибки чтения статистики
+                log::error!("Failed to check LLM budget: {}", err);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl<P: LlmProvider + ?Sized> LlmProvider for BudgetGuard<P> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    async fn complete(
+        &self,
+        messages: &[ChatMessage],
+        options: &GenerationOptions,
+    ) -> Result<ChatCompletion, LlmError> {
+        self.check()?;
+        self.inner.complete(messages, options).await
+    }
+
+    async fn complete_stream(
+        &self,
+        messages: &[ChatMessage],
+        options: &GenerationOptions,
+        partial: &watch::Sender<String>,
+    ) -> Result<ChatCompletion, LlmError> {
+        self.check()?;
+        self.inner.complete_stream(messages, options, partial).await
+    }
+}
+
+#[async_trait]
+impl<P: EmbeddingProvider + ?Sized> EmbeddingProvider for BudgetGuard<P> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    async fn embed(&self, text: &str, kind: EmbeddingKind) -> Result<Embedding, LlmError> {
+        self.check()?;
+        self.inner.embed(text, kind).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_prices_and_cost() {
+        let prices = PriceList::parse("yandexgpt=1.2; gpt-4o-mini=0.015:0.06").unwrap();
+        let usage = TokenUsage {
+            input_tokens: 1000,
+            completion_tokens: 500,
+            total_tokens: 1500,
+        };
+
+        assert!((prices.cost("yandexgpt", &usage) - 1.8).abs() < 1e-9);
+        assert!((prices.cost("gpt-4o-mini", &usage) - 0.045).abs() < 1e-9);
+        assert_eq!(prices.cost("unknown", &usage), 0.0);
+    }
+
+    #[test]
+    fn test_parse_rejects_garbage() {
+        assert!(PriceList::parse("yandexgpt").is_err());
+        assert!(PriceList::parse("yandexgpt=abc").is_err());
+    }
+}
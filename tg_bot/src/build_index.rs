@@ -0,0 +1,55 @@
+use clap::Parser;
+
+use tg_bot::config::{Cli, Config};
+use tg_bot::embeddings;
+use tg_bot::programs::ProgramRegistry;
+use tg_bot::vector_index::VectorIndex;
+
+/// Построить векторный индекс фрагментов программ для семантического поиска.
+/// Векторы неизменившихся фрагментов берутся из прежнего индекса.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Получить векторы всех фрагментов заново
+    #[arg(long)]
+    force: bool,
+    #[command(flatten)]
+    bot: Cli,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    pretty_env_logger::init();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    let config = Config::load_without_telegram(&args.bot)?;
+    if args.bot.check_config {
+        println!("Configuration is valid");
+        return Ok(());
+    }
+    let embedder =
+        embeddings::embeddings_from_config(&config.embeddings, &config.llm, &config.http)?
+            .ok_or_else(|| {
+                anyhow::anyhow!("embeddings.provider is \"none\", set EMBEDDINGS_PROVIDER=yandex")
+            })?;
+    let data = ProgramRegistry::load(&config.data.programs_dir)?;
+    let chunks = data.index().chunks();
+
+    let path = &config.embeddings.index_path;
+    let previous = if args.force {
+        None
+    } else {
+        VectorIndex::load(path)
+            .inspect_err(|e| log::warn!("Building vector index from scratch: {}", e))
+            .ok()
+    };
+    if let Some(index) = &previous
+        && index.is_current(embedder.model(), chunks)
+    {
+        println!("Vector index {} is up to date", path.display());
+        return Ok(());
+    }
+    let index = VectorIndex::build(embedder.as_ref(), chunks.to_vec(), previous.as_ref()).await?;
+    index.save(path)?;
+    println!("Saved {} chunks to {}", index.len(), path.display());
+    Ok(())
+}
@@ -0,0 +1,305 @@
+use serde::{Deserialize, Serialize};
+
+use crate::programs::Program;
+
+/// Фрагмент базы знаний: один вопрос FAQ, способ поступления, стипендия,
+/// курс, абзац описания или значение поля программы
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chunk {
+    /// `<slug>/<путь к полю>`, например `ai/faq[3]` или `ai_product/cost`.
+    /// Пока данные программы не меняются, id тоже не меняется.
+    pub id: String,
+    pub program: String,
+    /// Поле `MasterProgram` или `courses` из манифеста
+    pub field: String,
+    /// Номер элемента списка или абзаца текста в поле
+    pub index: Option<usize>,
+    /// Что это за фрагмент: "Стоимость обучения", "Частый вопрос"
+    pub label: String,
+    pub text: String,
+    /// Ссылка на раздел страницы программы, где показаны эти данные
+    pub url: String,
+    /// Заголовок этого раздела
+    pub section: String,
+}
+
+impl Chunk {
+    /// Путь к данным в программе: `faq[3]`, `cost`
+    pub fn field_path(&self) -> String {
+        match self.index {
+            Some(index) => format!("{}[{}]", self.field, index),
+            None => self.field.clone(),
+        }
+    }
+
+    /// Текст, понятный без контекста: "Стоимость обучения: 599 000 ₽"
+    pub fn render(&self) -> String {
+        format!("{}: {}", self.label, self.text)
+    }
+}
+
+/// Собирает фрагменты одной программы, пропуская пустые и повторы
+struct Chunks<'a> {
+    program: &'a Program,
+    chunks: Vec<Chunk>,
+}
+
+impl Chunks<'_> {
+    fn push(&mut self, field: &str, index: Option<usize>, label: &str, text: &str) {
+        let text = clean(text);
+        // Парсер иногда находит один и тот же блок на странице дважды
+        let duplicate = self
+            .chunks
+            .iter()
+            .any(|chunk| chunk.field == field && chunk.text == text);
+        if text.is_empty() || duplicate {
+            return;
+        }
+        let (anchor, section) = section(field);
+        let url = match anchor {
+            Some(anchor) => format!("{}#{}", self.program.url, anchor),
+            None => self.program.url.clone(),
+        };
+        let mut chunk = Chunk {
+            id: String::new(),
+            program: self.program.slug.clone(),
+            field: field.to_string(),
+            index,
+            label: label.to_string(),
+            text,
+            url,
+            section: section.to_string(),
+        };
+        chunk.id = format!("{}/{}", chunk.program, chunk.field_path());
+        self.chunks.push(chunk);
+    }
+
+    /// Поле с текстом из нескольких абзацев: фрагмент на абзац
+    fn paragraphs(&mut self, field: &str, label: &str, text: &str) {
+        let paragraphs = text.lines().filter(|line| !clean(line).is_empty());
+        for (index, paragraph) in paragraphs.enumerate() {
+            self.push(field, Some(index), label, paragraph);
+        }
+    }
+
+    /// Короткий список одним фрагментом
+    fn list(&mut self, field: &str, label: &str, items: &[String]) {
+        self.push(field, None, label, &items.join("; "));
+    }
+
+    fn count(&mut self, field: &str, label: &str, count: Option<u32>) {
+        // None - парсер не нашел число на странице
+        if let Some(count) = count {
+            self.push(field, None, label, &count.to_string());
+        }
+    }
+
+    fn flag(&mut self, field: &str, label: &str, value: bool, yes: &str) {
+        // false - это и "нет", и "парсер не нашел на странице", так что отрицания не утверждаем
+        if value {
+            self.push(field, None, label, yes);
+        }
+    }
+}
+
+/// Раздел страницы программы на abit.itmo.ru, где показано поле: якорь и заголовок
+pub fn section(field: &str) -> (Option<&'static str>, &'static str) {
+    match field {
+        "description" => (Some("about"), "О программе"),
+        "exam_dates" | "admission_methods" => (Some("admission"), "Как поступить"),
+        "career_opportunities" | "average_salary" => (Some("careers"), "Карьера"),
+        "team" => (Some("team"), "Команда программы"),
+        "partners" => (Some("partners"), "Партнеры программы"),
+        "scholarships" => (Some("scholarship"), "Стипендии"),
+        "international_opportunities" => (Some("opportunities"), "Международные возможности"),
+        "faq" => (Some("faq"), "Часто задаваемые вопросы"),
+        "courses" => (Some("study-plan"), "Учебный план"),
+        // Стоимость, места, контакты и остальные сведения - в шапке страницы
+        _ => (None, "Основные сведения"),
+    }
+}
+
+/// Разметка, которую парсер оставил в тексте, и переносы строк посреди предложений
+fn clean(text: &str) -> String {
+    text.replace("<!-- -->", "")
+        .replace("&nbsp;", " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Фрагменты программы в порядке полей `MasterProgram`, курсы в конце
+pub fn program_chunks(program: &Program) -> Vec<Chunk> {
+    let mut chunks = Chunks {
+        program,
+        chunks: Vec::new(),
+    };
+    if let Some(info) = &program.info {
+        chunks.push("title", None, "Название программы", &info.title);
+        chunks.paragraphs("description", "Описание программы", &info.description);
+        chunks.push("institute", None, "Институт", &info.institute);
+        chunks.push("study_form", None, "Форма обучения", &info.study_form);
+        chunks.push("duration", None, "Срок обучения", &info.duration);
+        chunks.push("language", None, "Язык обучения", &info.language);
+        chunks.push("cost", None, "Стоимость обучения", &info.cost);
+        chunks.flag("dormitory", "Общежитие", info.dormitory, "предоставляется");
+        chunks.flag(
+            "military_center",
+            "Военный учебный центр",
+            info.military_center,
+            "есть",
+        );
+        chunks.flag(
+            "accreditation",
+            "Государственная аккредитация",
+            info.accreditation,
+            "есть",
+        );
+        chunks.list(
+            "special_programs",
+            "Особенности программы",
+            &info.special_programs,
+        );
+        chunks.push(
+            "direction_code",
+            None,
+            "Код направления подготовки",
+            &info.direction_code,
+        );
+        chunks.push(
+            "direction_name",
+            None,
+            "Направление подготовки",
+            &info.direction_name,
+        );
+        chunks.count("budget_places", "Бюджетные места", info.budget_places);
+        chunks.count("target_places", "Целевые места", info.target_places);
+        chunks.count("contract_places", "Контрактные места", info.contract_places);
+        let manager = &info.manager;
+        let contacts: Vec<String> = [&manager.name, &manager.email, &manager.phone]
+            .into_iter()
+            .filter(|value| !value.trim().is_empty())
+            .cloned()
+            .collect();
+        chunks.push("manager", None, "Менеджер программы", &contacts.join(", "));
+        let links: Vec<String> = info
+            .social_links
+            .iter()
+            .map(|link| format!("{}: {}", link.platform, link.url))
+            .collect();
+        chunks.list("social_links", "Соцсети и сайты программы", &links);
+        chunks.list(
+            "exam_dates",
+            "Даты вступительных экзаменов",
+            &info.exam_dates,
+        );
+        for (index, method) in info.admission_methods.iter().enumerate() {
+            chunks.push(
+                "admission_methods",
+                Some(index),
+                "Способ поступления",
+                &format!("{}: {}", method.name, method.description),
+            );
+        }
+        chunks.paragraphs(
+            "career_opportunities",
+            "Карьера выпускников",
+            &info.career_opportunities,
+        );
+        chunks.push(
+            "average_salary",
+            None,
+            "Доход выпускников",
+            &info.average_salary,
+        );
+        for (index, member) in info.team.iter().enumerate() {
+            let mut text = format!("{}, {}", member.name, member.position);
+            if let Some(degree) = &member.degree {
+                text.push_str(", ");
+                text.push_str(degree);
+            }
+            chunks.push("team", Some(index), "Команда программы", &text);
+        }
+        chunks.list("partners", "Партнеры программы", &info.partners);
+        for (index, scholarship) in info.scholarships.iter().enumerate() {
+            chunks.push(
+                "scholarships",
+                Some(index),
+                "Стипендия",
+                &format!("{}: {}", scholarship.name, scholarship.amount),
+            );
+        }
+        chunks.list(
+            "international_opportunities",
+            "Международные возможности",
+            &info.international_opportunities,
+        );
+        for (index, item) in info.faq.iter().enumerate() {
+            chunks.push(
+                "faq",
+                Some(index),
+                "Частый вопрос",
+                &format!("{} {}", item.question, item.answer),
+            );
+        }
+    }
+    for (index, course) in program.courses.iter().enumerate() {
+        // С учебным планом курс описан подробно: семестр, з.е., форма контроля
+        let text = match program.curriculum.get(index) {
+            Some(discipline) => format!("{}: {}", course, discipline.describe()),
+            None => course.clone(),
+        };
+        chunks.push("courses", Some(index), "Курс программы", &text);
+    }
+    chunks.chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::programs::ProgramRegistry;
+
+    #[test]
+    fn test_program_chunks() {
+        let registry = ProgramRegistry::load("data/programs").unwrap();
+        let ai = registry.get("ai").unwrap();
+        let chunks = program_chunks(ai);
+
+        let cost = chunks.iter().find(|c| c.id == "ai/cost").unwrap();
+        assert_eq!(cost.render(), "Стоимость обучения: 599 000 ₽");
+        assert_eq!(cost.field_path(), "cost");
+        assert_eq!(cost.url, "https://abit.itmo.ru/program/master/ai");
+
+        let mut ids: Vec<&str> = chunks.iter().map(|c| c.id.as_str()).collect();
+        ids.sort();
+        ids.dedup();
+        assert_eq!(ids.len(), chunks.len(), "ids are not unique");
+        // Id указывает на элемент в данных
+        let faq = chunks.iter().find(|c| c.id == "ai/faq[2]").unwrap();
+        let item = &ai.info.as_ref().unwrap().faq[2];
+        assert!(faq.text.starts_with(item.question.trim()));
+        assert_eq!(faq.url, "https://abit.itmo.ru/program/master/ai#faq");
+        assert_eq!(faq.section, "Часто задаваемые вопросы");
+        assert_eq!(
+            chunks.iter().filter(|c| c.field == "courses").count(),
+            ai.courses.len()
+        );
+        // Без разметки и сырого json
+        for chunk in &chunks {
+            assert!(!chunk.text.contains("<!--"), "{}", chunk.text);
+            assert!(!chunk.text.contains("&nbsp;"), "{}", chunk.text);
+            assert!(!chunk.text.contains('{'), "{}", chunk.text);
+        }
+        assert_eq!(program_chunks(ai), chunks);
+
+        // Непроставленный флаг не превращается в "Общежитие: не предоставляется"
+        assert!(chunks.iter().any(|c| c.id == "ai/dormitory"));
+        let mut without_dormitory = ai.clone();
+        without_dormitory.info.as_mut().unwrap().dormitory = false;
+        assert!(
+            program_chunks(&without_dormitory)
+                .iter()
+                .all(|c| c.field != "dormitory")
+        );
+    }
+}
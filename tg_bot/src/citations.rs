@@ -0,0 +1,263 @@
+use crate::chunker::Chunk;
+use crate::programs::ProgramRegistry;
+
+/// Фрагменты, попавшие в промпт, под номерами, на которые модель ссылается
+/// в ответе: "Стоимость обучения 599 000 ₽ [1]"
+#[derive(Debug, Default)]
+pub struct Citations<'a> {
+    chunks: Vec<&'a Chunk>,
+}
+
+impl<'a> Citations<'a> {
+    pub fn new() -> Self {
+        Citations::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Фрагменты для промпта, по одному на строку, с номерами для ссылок.
+    /// Фрагмент, уже попавший в промпт, сохраняет свой номер.
+    pub fn render(&mut self, chunks: &[&'a Chunk]) -> String {
+        chunks
+            .iter()
+            .map(|chunk| format!("[{}] {}", self.number(chunk), chunk.render()))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn number(&mut self, chunk: &'a Chunk) -> usize {
+        match self.chunks.iter().position(|c| c.id == chunk.id) {
+            Some(index) => index + 1,
+            None => {
+                self.chunks.push(chunk);
+                self.chunks.len()
+            }
+        }
+    }
+
+    /// Фрагменты, на которые ссылается ответ, с номерами. Номера, которых
+    /// не было в промпте, модель придумала, они пропускаются.
+    pub fn cited(&self, answer: &str) -> Vec<(usize, &'a Chunk)> {
+        markers(answer)
+            .into_iter()
+            .filter_map(|number| Some((number, *self.chunks.get(number.checked_sub(1)?)?)))
+            .collect()
+    }
+
+    /// Список источников для конца ответа: разделы страниц программ, на которые
+    /// ссылается ответ, `None`, если ссылок нет
+    pub fn footer(&self, answer: &str, data: &ProgramRegistry) -> Option<String> {
+        // Несколько фрагментов из одного раздела - одна ссылка
+        let mut sources: Vec<(Vec<usize>, &Chunk)> = Vec::new();
+        for (number, chunk) in self.cited(answer) {
+            match sources.iter_mut().find(|(_, c)| c.url == chunk.url) {
+                Some((numbers, _)) => numbers.push(number),
+                None => sources.push((vec![number], chunk)),
+            }
+        }
+        if sources.is_empty() {
+            return None;
+        }
+        let lines: Vec<String> = sources
+            .into_iter()
+            .map(|(mut numbers, chunk)| {
+                numbers.sort();
+                let numbers: Vec<String> = numbers.iter().map(usize::to_string).collect();
+                let program = data
+                    .get(&chunk.program)
+                    .map_or(chunk.program.as_str(), |p| p.short_name.as_str());
+                format!(
+                    "[{}] {} · {}: {}",
+                    numbers.join(", "),
+                    program,
+                    chunk.section,
+                    chunk.url
+                )
+            })
+            .collect();
+        Some(format!("Источники:\n{}", lines.join("\n")))
+    }
+}
+
+/// Содержимое `[...]`, если это ссылки на фрагменты: `1` или `1, 3`
+fn parse_marker(inner: &str) -> Option<Vec<usize>> {
+    inner
+        .split(',')
+        .map(|number| number.trim().parse().ok())
+        .collect()
+}
+
+/// Номера фрагментов из ссылок `[1]`, `[1, 3]`, `[2][4]` в порядке появления, без повторов
+pub fn markers(text: &str) -> Vec<usize> {
+    let mut numbers = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('[') {
+        rest = &rest[start + 1..];
+        let Some(end) = rest.find(']') else {
+            break;
+        };
+        for number in parse_marker(&rest[..end]).unwrap_or_default() {
+            if !numbers.contains(&number) {
+                numbers.push(number);
+            }
+        }
+        rest = &rest[end + 1..];
+    }
+    numbers
+}
+
+/// Текст без ссылок на фрагменты
+pub fn strip_markers(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('[') {
+        result.push_str(&rest[..start]);
+        let tail = &rest[start + 1..];
+        match tail.find(']') {
+            Some(end) if parse_marker(&tail[..end]).is_some() => rest = &tail[end + 1..],
+            _ => {
+                result.push('[');
+                rest = tail;
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// Предложения ответа: по строкам и по `.`, `!`, `?` перед пробелом.
+/// Точка после цифры не разделяет: это пункт списка "1. " или конец числа.
+pub fn sentences(text: &str) -> Vec<&str> {
+    let mut sentences = Vec::new();
+    for line in text.lines() {
+        let mut start = 0;
+        let mut prev = None;
+        let mut chars = line.char_indices().peekable();
+        while let Some((i, c)) = chars.next() {
+            let next_is_space = chars.peek().is_some_and(|(_, next)| next.is_whitespace());
+            if matches!(c, '.' | '!' | '?')
+                && next_is_space
+                && !prev.is_some_and(|p: char| p.is_ascii_digit())
+            {
+                sentences.push(&line[start..i + c.len_utf8()]);
+                start = i + c.len_utf8();
+            }
+            prev = Some(c);
+        }
+        sentences.push(&line[start..]);
+    }
+    sentences
+        .into_iter()
+        .map(str::trim)
+        .filter(|sentence| !sentence.is_empty())
+        .collect()
+}
+
+/// Предложение без номера пункта списка: "1. Подайте заявление"
+fn strip_list_number(sentence: &str) -> &str {
+    let rest = sentence.trim_start_matches(|c: char| c.is_ascii_digit());
+    match rest.strip_prefix(['.', ')']) {
+        Some(item) if rest.len() < sentence.len() && item.starts_with(' ') => item,
+        _ => sentence,
+    }
+}
+
+/// Предложения ответа с числами, но без ссылки на фрагмент: стоимость, даты
+/// и количество мест, которые модель могла выдумать. Ссылка сразу после
+/// точки ("... 599 000 ₽. [1]") относится к предыдущему предложению.
+pub fn uncited_numbers(answer: &str) -> Vec<&str> {
+    let sentences = sentences(answer);
+    let starts_with_marker = |sentence: &str| {
+        sentence
+            .strip_prefix('[')
+            .and_then(|rest| rest.split_once(']'))
+            .is_some_and(|(inner, _)| parse_marker(inner).is_some())
+    };
+    sentences
+        .iter()
+        .enumerate()
+        .filter(|&(i, sentence)| {
+            let cited = !markers(sentence).is_empty()
+                || sentences
+                    .get(i + 1)
+                    .is_some_and(|next| starts_with_marker(next));
+            !cited
+                && strip_markers(strip_list_number(sentence))
+                    .chars()
+                    .any(|c| c.is_ascii_digit())
+        })
+        .map(|(_, sentence)| *sentence)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(id: &str, section: &str, url: &str) -> Chunk {
+        Chunk {
+            id: id.to_string(),
+            program: "ai".to_string(),
+            field: "faq".to_string(),
+            index: None,
+            label: "Частый вопрос".to_string(),
+            text: id.to_string(),
+            url: url.to_string(),
+            section: section.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_markers() {
+        assert_eq!(markers("Да [2]. Стоимость [1, 3][2]"), vec![2, 1, 3]);
+        assert_eq!(markers("[ссылка] [12.08] [ 4 ]"), vec![4]);
+        assert_eq!(markers("нет ссылок ["), Vec::<usize>::new());
+    }
+
+    #[test]
+    fn test_uncited_numbers() {
+        let answer = "Обучение стоит 599 000 ₽ [1]. Бюджетных мест 51!\n\
+            Экзамен 12.08.2025. [2]\n1. Подайте заявление\nОбщежитие есть.";
+        assert_eq!(uncited_numbers(answer), vec!["Бюджетных мест 51!"]);
+        assert!(uncited_numbers("Подробнее на сайте [3].").is_empty());
+    }
+
+    #[test]
+    fn test_render_and_footer() {
+        let data = ProgramRegistry::load("data/programs").unwrap();
+        let faq = chunk("ai/faq[0]", "Часто задаваемые вопросы", "https://x/ai#faq");
+        let faq2 = chunk("ai/faq[1]", "Часто задаваемые вопросы", "https://x/ai#faq");
+        let cost = chunk("ai/cost", "Основные сведения", "https://x/ai");
+
+        let mut citations = Citations::new();
+        let prompt = citations.render(&[&faq, &cost]);
+        assert_eq!(
+            prompt,
+            "[1] Частый вопрос: ai/faq[0]\n[2] Частый вопрос: ai/cost"
+        );
+        // Повторный фрагмент сохраняет номер
+        assert_eq!(
+            citations.render(&[&cost, &faq2]),
+            "[2] Частый вопрос: ai/cost\n[3] Частый вопрос: ai/faq[1]"
+        );
+        assert_eq!(citations.len(), 3);
+
+        assert_eq!(citations.footer("Без ссылок", &data), None);
+        assert_eq!(citations.footer("Выдумано [7]", &data), None);
+        let footer = citations.footer("Да [3]. Цена [2], подробно [1]", &data);
+        assert_eq!(
+            footer.as_deref(),
+            Some(
+                "Источники:\n\
+                 [1, 3] AI · Часто задаваемые вопросы: https://x/ai#faq\n\
+                 [2] AI · Основные сведения: https://x/ai"
+            )
+        );
+    }
+}
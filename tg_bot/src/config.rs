@@ -0,0 +1,888 @@
+use std::fmt::Display;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use clap::Parser;
+use serde::{Deserialize, Deserializer};
+
+use crate::answer_cache::CacheConfig;
+use crate::billing::{Budget, PriceList};
+use crate::http_client::HttpClientConfig;
+use crate::llm_provider::GenerationOptions;
+use crate::programs::PROGRAM_FIELDS;
+use crate::prompts::PromptSet;
+use crate::rate_limit::RateLimitConfig;
+use crate::resilience::ResilienceConfig;
+
+/// Файл настроек по умолчанию, если он есть в рабочем каталоге
+const DEFAULT_CONFIG_PATH: &str = "config.toml";
+
+#[derive(Debug, Clone, Parser)]
+#[command(about = "Telegram бот с ответами о магистратурах ITMO")]
+pub struct Cli {
+    /// TOML файл с настройками (по умолчанию `CONFIG_PATH` или `config.toml`)
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    #[arg(long)]
+    pub programs_dir: Option<PathBuf>,
+    #[arg(long)]
+    pub database_path: Option<PathBuf>,
+    #[arg(long)]
+    pub prompts_dir: Option<PathBuf>,
+    /// `yandex` или `openai`
+    #[arg(long)]
+    pub provider: Option<String>,
+    #[arg(long)]
+    pub model: Option<String>,
+    #[arg(long)]
+    pub temperature: Option<f32>,
+    #[arg(long)]
+    pub max_tokens: Option<u32>,
+    /// Отвечать локальным мок-сервером YandexGPT вместо настоящей модели,
+    /// ключи API не нужны
+    #[arg(long)]
+    pub mock_llm: bool,
+    /// Проверить настройки и выйти
+    #[arg(long)]
+    pub check_config: bool,
+}
+
+/// Все настройки бота. Источники по возрастанию приоритета: значения
+/// по умолчанию, TOML файл, переменные окружения, флаги командной строки.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub telegram: TelegramConfig,
+    pub llm: LlmConfig,
+    pub generation: GenerationOptions,
+    pub http: HttpClientConfig,
+    pub resilience: ResilienceConfig,
+    pub budget: Budget,
+    pub rate_limit: RateLimitConfig,
+    pub cache: CacheConfig,
+    pub intent: IntentConfig,
+    pub dialogue: DialogueConfig,
+    pub data: DataConfig,
+    pub courses: CoursesConfig,
+    pub retrieval: RetrievalConfig,
+    pub embeddings: EmbeddingsConfig,
+    pub verification: VerificationConfig,
+    pub prompts: PromptConfig,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelegramConfig {
+    pub token: Option<String>,
+    /// Telegram id администраторов
+    pub admins: Vec<u64>,
+    /// Как часто обновлять сообщение, пока ответ генерируется
+    #[serde(rename = "stream_edit_interval_ms", deserialize_with = "millis")]
+    pub stream_edit_interval: Duration,
+}
+
+impl Default for TelegramConfig {
+    fn default() -> Self {
+        TelegramConfig {
+            token: None,
+            admins: Vec::new(),
+            stream_edit_interval: Duration::from_millis(1500),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LlmConfig {
+    /// `yandex` или `openai`
+    pub provider: String,
+    /// Имя модели, для YandexGPT по умолчанию `yandexgpt`
+    pub model: Option<String>,
+    /// Версия модели YandexGPT в конце URI: `latest`, `rc`, `deprecated`
+    pub model_version: Option<String>,
+    /// Цены моделей в формате `PriceList::parse`
+    pub prices: String,
+    pub yandex: YandexConfig,
+    pub openai: OpenAIConfig,
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        LlmConfig {
+            provider: "yandex".to_string(),
+            model: None,
+            model_version: None,
+            prices: "yandexgpt=1.2:1.2;text-search-query=0.01".to_string(),
+            yandex: YandexConfig::default(),
+            openai: OpenAIConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct YandexConfig {
+    pub api_key: Option<String>,
+    pub api_url: String,
+    pub folder_id: Option<String>,
+}
+
+impl Default for YandexConfig {
+    fn default() -> Self {
+        YandexConfig {
+            api_key: None,
+            api_url: "https://llm.api.cloud.yandex.net/foundationModels/v1/completion".to_string(),
+            folder_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OpenAIConfig {
+    pub api_key: Option<String>,
+    pub api_url: String,
+}
+
+impl Default for OpenAIConfig {
+    fn default() -> Self {
+        OpenAIConfig {
+            api_key: None,
+            api_url: "https://api.openai.com/v1/chat/completions".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct IntentConfig {
+    /// Уточнять неуверенную классификацию у LLM
+    pub llm_fallback: bool,
+    pub llm_threshold: f32,
+}
+
+impl Default for IntentConfig {
+    fn default() -> Self {
+        IntentConfig {
+            llm_fallback: false,
+            llm_threshold: crate::intent::DEFAULT_LLM_THRESHOLD,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DialogueConfig {
+    pub max_turns: usize,
+    pub max_tokens: usize,
+}
+
+impl Default for DialogueConfig {
+    fn default() -> Self {
+        DialogueConfig {
+            max_turns: 5,
+            max_tokens: 1500,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DataConfig {
+    /// Каталог с манифестами программ
+    pub programs_dir: PathBuf,
+    pub database_path: PathBuf,
+    /// Поля программы, из которых LLM выбирает релевантные вопросу
+    pub fields: Vec<String>,
+}
+
+impl Default for DataConfig {
+    fn default() -> Self {
+        DataConfig {
+            programs_dir: PathBuf::from("data/programs"),
+            database_path: PathBuf::from("data/bot.sqlite3"),
+            fields: PROGRAM_FIELDS.iter().map(|f| f.to_string()).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CoursesConfig {
+    /// Сколько курсов добавлять в промпт
+    pub max_in_prompt: usize,
+    /// Сколько курсов показывать в упрощенном ответе без LLM
+    pub max_in_fallback: usize,
+}
+
+impl Default for CoursesConfig {
+    fn default() -> Self {
+        CoursesConfig {
+            max_in_prompt: 10,
+            max_in_fallback: 3,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RetrievalConfig {
+    /// Сколько найденных фрагментов добавлять в промпт
+    pub max_passages: usize,
+    /// Доля слов вопроса, которые должны найтись в лучшем фрагменте,
+    /// чтобы не спрашивать у LLM релевантные поля
+    pub min_coverage: f32,
+}
+
+impl Default for RetrievalConfig {
+    fn default() -> Self {
+        RetrievalConfig {
+            max_passages: 5,
+            min_coverage: 0.6,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct EmbeddingsConfig {
+    /// `none` (семантический поиск выключен) или `yandex`,
+    /// ключ и каталог берутся из `[llm.yandex]`
+    pub provider: String,
+    pub api_url: String,
+    /// Модель для фрагментов программ
+    pub doc_model: String,
+    /// Модель для вопросов пользователей
+    pub query_model: String,
+    /// Файл с векторами фрагментов, см. `vector_index::VectorIndex`
+    pub index_path: PathBuf,
+    /// Перестраивать индекс при старте, если данные программ изменились
+    pub rebuild_on_start: bool,
+    /// Сколько близких по смыслу фрагментов добавлять в промпт
+    pub top_k: usize,
+    /// Минимальная косинусная близость фрагмента к вопросу
+    pub min_score: f32,
+}
+
+impl Default for EmbeddingsConfig {
+    fn default() -> Self {
+        EmbeddingsConfig {
+            provider: "none".to_string(),
+            api_url: "https://llm.api.cloud.yandex.net/foundationModels/v1/textEmbedding"
+                .to_string(),
+            doc_model: "text-search-doc".to_string(),
+            query_model: "text-search-query".to_string(),
+            index_path: PathBuf::from("data/vector_index.json"),
+            rebuild_on_start: true,
+            top_k: 3,
+            min_score: 0.5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct VerificationConfig {
+    /// Что делать с ответом, в котором есть числа, даты или адреса, которых нет
+    /// в данных программы: `off`, `log`, `append` (дописать поправку, по умолчанию),
+    /// `rewrite` (убрать такие предложения) или `regenerate` (попросить модель
+    /// ответить заново, это еще один запрос к LLM)
+    pub action: String,
+    /// Числа меньше этого не проверяются: "2 года", "3 способа поступления"
+    pub min_number: u64,
+}
+
+impl Default for VerificationConfig {
+    fn default() -> Self {
+        VerificationConfig {
+            action: "append".to_string(),
+            min_number: 10,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PromptConfig {
+    /// Каталог с шаблонами промптов `<name>.txt`, см. `prompts::Template`
+    pub dir: PathBuf,
+}
+
+impl Default for PromptConfig {
+    fn default() -> Self {
+        PromptConfig {
+            dir: PathBuf::from("prompts"),
+        }
+    }
+}
+
+/// Ошибки настроек: собираются все сразу, чтобы исправить их за один заход
+#[derive(Debug, thiserror::Error)]
+#[error("invalid configuration:\n  {}", .0.join("\n  "))]
+pub struct ConfigError(pub Vec<String>);
+
+impl Config {
+    /// Собрать настройки из файла, окружения (включая `.env`) и флагов
+    pub fn load(cli: &Cli) -> Result<Config, ConfigError> {
+        Config::load_layers(cli, true)
+    }
+
+    /// То же для бинарников, которым не нужен Telegram, например `eval`
+    pub fn load_without_telegram(cli: &Cli) -> Result<Config, ConfigError> {
+        Config::load_layers(cli, false)
+    }
+
+    fn load_layers(cli: &Cli, require_telegram: bool) -> Result<Config, ConfigError> {
+        let path = cli.config.clone().or_else(|| {
+            env_var("CONFIG_PATH")
+                .filter(|v| !v.is_empty())
+                .map(PathBuf::from)
+        });
+        let file = match &path {
+            Some(path) => Some(read_file(path)?),
+            None if Path::new(DEFAULT_CONFIG_PATH).exists() => {
+                Some(read_file(Path::new(DEFAULT_CONFIG_PATH))?)
+            }
+            None => None,
+        };
+        Config::from_layers(file.as_deref(), env_var, cli, require_telegram)
+    }
+
+    fn from_layers(
+        file: Option<&str>,
+        var: impl Fn(&str) -> Option<String>,
+        cli: &Cli,
+        require_telegram: bool,
+    ) -> Result<Config, ConfigError> {
+        let mut errors = Vec::new();
+        let mut config = match file.map(toml::from_str::<Config>) {
+            Some(Ok(config)) => config,
+            Some(Err(err)) => {
+                errors.push(format!("config file: {}", err.message()));
+                Config::default()
+            }
+            None => Config::default(),
+        };
+        config.apply_env(&var, &mut errors);
+        config.apply_cli(cli);
+        if require_telegram
+            && config
+                .telegram
+                .token
+                .as_deref()
+                .is_none_or(|token| token.trim().is_empty())
+        {
+            errors.push("telegram.token (TELOXIDE_TOKEN) is not set".to_string());
+        }
+        errors.extend(config.validate());
+        if errors.is_empty() {
+            Ok(config)
+        } else {
+            Err(ConfigError(errors))
+        }
+    }
+
+    /// Переменные окружения с прежними именами, пустые значения игнорируются
+    fn apply_env(&mut self, var: &impl Fn(&str) -> Option<String>, errors: &mut Vec<String>) {
+        let mut env = Env { var, errors };
+
+        env.set("TELOXIDE_TOKEN", &mut self.telegram.token);
+        if let Some(admins) = env.list("ADMIN_USER_IDS") {
+            self.telegram.admins = admins;
+        }
+        env.millis(
+            "STREAM_EDIT_INTERVAL_MS",
+            &mut self.telegram.stream_edit_interval,
+        );
+
+        let llm = &mut self.llm;
+        env.set("LLM_PROVIDER", &mut llm.provider);
+        // Прежнее имя из .env.example, где рядом может стоять LLM_PROVIDER=yandex
+        if llm.provider == "openai" {
+            env.set("OPENAI_MODEL", &mut llm.model);
+        }
+        env.set("LLM_MODEL", &mut llm.model);
+        env.set("LLM_MODEL_VERSION", &mut llm.model_version);
+        env.set("LLM_PRICES", &mut llm.prices);
+        env.set("YANDEX_GPT_API_KEY", &mut llm.yandex.api_key);
+        env.set("YANDEX_GPT_API_URL", &mut llm.yandex.api_url);
+        env.set("YANDEX_FOLDER_ID", &mut llm.yandex.folder_id);
+        env.set("OPENAI_API_KEY", &mut llm.openai.api_key);
+        env.set("OPENAI_API_URL", &mut llm.openai.api_url);
+
+        env.set("LLM_TEMPERATURE", &mut self.generation.temperature);
+        env.set("LLM_MAX_TOKENS", &mut self.generation.max_tokens);
+
+        let http = &mut self.http;
+        env.secs("HTTP_CONNECT_TIMEOUT_SECS", &mut http.connect_timeout);
+        env.secs("HTTP_READ_TIMEOUT_SECS", &mut http.read_timeout);
+        env.set("HTTP_PROXY_URL", &mut http.proxy);
+        env.set("HTTP_USER_AGENT", &mut http.user_agent);
+        env.secs("HTTP2_KEEP_ALIVE_SECS", &mut http.http2_keep_alive_interval);
+        env.secs("HTTP_POOL_IDLE_TIMEOUT_SECS", &mut http.pool_idle_timeout);
+
+        let resilience = &mut self.resilience;
+        env.set("LLM_MAX_RETRIES", &mut resilience.max_retries);
+        env.millis("LLM_RETRY_BASE_DELAY_MS", &mut resilience.base_delay);
+        env.millis("LLM_RETRY_MAX_DELAY_MS", &mut resilience.max_delay);
+        env.millis("LLM_REQUEST_DEADLINE_MS", &mut resilience.deadline);
+        env.set(
+            "LLM_BREAKER_FAILURE_THRESHOLD",
+            &mut resilience.failure_threshold,
+        );
+        env.secs("LLM_BREAKER_COOLDOWN_SECS", &mut resilience.cooldown);
+
+        env.set("LLM_DAILY_BUDGET", &mut self.budget.daily);
+        env.set("LLM_MONTHLY_BUDGET", &mut self.budget.monthly);
+
+        let rate_limit = &mut self.rate_limit;
+        env.set("RATE_LIMIT_CHAT_BURST", &mut rate_limit.chat_burst);
+        env.set(
+            "RATE_LIMIT_CHAT_PER_MINUTE",
+            &mut rate_limit.chat_per_minute,
+        );
+        env.set("RATE_LIMIT_GLOBAL_BURST", &mut rate_limit.global_burst);
+        env.set(
+            "RATE_LIMIT_GLOBAL_PER_MINUTE",
+            &mut rate_limit.global_per_minute,
+        );
+        let mut quota = rate_limit.daily_quota.unwrap_or(0);
+        env.set("DAILY_QUESTION_QUOTA", &mut quota);
+        rate_limit.daily_quota = Some(quota).filter(|&quota| quota > 0);
+        env.set("MAX_INPUT_CHARS", &mut rate_limit.max_input_chars);
+        env.set("ACCESS_ALLOWLIST_ONLY", &mut rate_limit.allowlist_only);
+
+        env.secs("ANSWER_CACHE_TTL_SECS", &mut self.cache.ttl);
+        env.set("ANSWER_CACHE_MAX_ENTRIES", &mut self.cache.max_entries);
+
+        env.set("INTENT_LLM_FALLBACK", &mut self.intent.llm_fallback);
+        env.set("INTENT_LLM_THRESHOLD", &mut self.intent.llm_threshold);
+
+        env.set("DIALOGUE_MAX_TURNS", &mut self.dialogue.max_turns);
+        env.set("DIALOGUE_MAX_TOKENS", &mut self.dialogue.max_tokens);
+
+        env.set("RETRIEVAL_MAX_PASSAGES", &mut self.retrieval.max_passages);
+        env.set("RETRIEVAL_MIN_COVERAGE", &mut self.retrieval.min_coverage);
+
+        let embeddings = &mut self.embeddings;
+        env.set("EMBEDDINGS_PROVIDER", &mut embeddings.provider);
+        env.set("EMBEDDINGS_API_URL", &mut embeddings.api_url);
+        env.set("EMBEDDINGS_INDEX_PATH", &mut embeddings.index_path);
+        env.set(
+            "EMBEDDINGS_REBUILD_ON_START",
+            &mut embeddings.rebuild_on_start,
+        );
+        env.set("EMBEDDINGS_TOP_K", &mut embeddings.top_k);
+        env.set("EMBEDDINGS_MIN_SCORE", &mut embeddings.min_score);
+
+        env.set("VERIFICATION_ACTION", &mut self.verification.action);
+        env.set("VERIFICATION_MIN_NUMBER", &mut self.verification.min_number);
+
+        env.set("PROGRAMS_DIR", &mut self.data.programs_dir);
+        env.set("DATABASE_PATH", &mut self.data.database_path);
+        env.set("PROMPTS_DIR", &mut self.prompts.dir);
+    }
+
+    fn apply_cli(&mut self, cli: &Cli) {
+        let Cli {
+            config: _,
+            programs_dir,
+            database_path,
+            prompts_dir,
+            provider,
+            model,
+            temperature,
+            max_tokens,
+            mock_llm,
+            check_config: _,
+        } = cli.clone();
+        if let Some(programs_dir) = programs_dir {
+            self.data.programs_dir = programs_dir;
+        }
+        if let Some(database_path) = database_path {
+            self.data.database_path = database_path;
+        }
+        if let Some(prompts_dir) = prompts_dir {
+            self.prompts.dir = prompts_dir;
+        }
+        if let Some(provider) = provider {
+            self.llm.provider = provider;
+        }
+        if model.is_some() {
+            self.llm.model = model;
+        }
+        if let Some(temperature) = temperature {
+            self.generation.temperature = temperature;
+        }
+        if let Some(max_tokens) = max_tokens {
+            self.generation.max_tokens = max_tokens;
+        }
+        // Адрес мок-сервера подставляется после его запуска.
+        // Векторы мока не годятся для настоящего индекса, поэтому семантический поиск выключается
+        if mock_llm {
+            self.embeddings.provider = "none".to_string();
+            self.llm.provider = "yandex".to_string();
+            self.llm
+                .yandex
+                .api_key
+                .get_or_insert_with(|| "mock".to_string());
+            self.llm
+                .yandex
+                .folder_id
+                .get_or_insert_with(|| "mock".to_string());
+        }
+    }
+
+    /// Все проблемы настроек, кроме токена Telegram, пустой список - настройки корректны
+    pub fn validate(&self) -> Vec<String> {
+        let mut errors = Vec::new();
+        let mut require = |value: &Option<String>, name: &str| {
+            if value.as_deref().is_none_or(|v| v.trim().is_empty()) {
+                errors.push(format!("{name} is not set"));
+            }
+        };
+        // Ключи YandexGPT нужны и для векторов, даже если ответы генерирует другой провайдер
+        if self.llm.provider == "yandex" || self.embeddings.provider == "yandex" {
+            require(
+                &self.llm.yandex.api_key,
+                "llm.yandex.api_key (YANDEX_GPT_API_KEY)",
+            );
+            require(
+                &self.llm.yandex.folder_id,
+                "llm.yandex.folder_id (YANDEX_FOLDER_ID)",
+            );
+        }
+        match self.llm.provider.as_str() {
+            "yandex" => {}
+            "openai" => {
+                require(
+                    &self.llm.openai.api_key,
+                    "llm.openai.api_key (OPENAI_API_KEY)",
+                );
+                require(&self.llm.model, "llm.model (LLM_MODEL)");
+            }
+            other => errors.push(format!(
+                "llm.provider: unknown provider {other:?}, expected \"yandex\" or \"openai\""
+            )),
+        }
+        if let Err(err) = PriceList::parse(&self.llm.prices) {
+            errors.push(format!("llm.prices: {err}"));
+        }
+
+        let temperature = self.generation.temperature;
+        if !(0.0..=2.0).contains(&temperature) {
+            errors.push(format!(
+                "generation.temperature: {temperature} is outside 0..=2"
+            ));
+        }
+        if self.generation.max_tokens == 0 {
+            errors.push("generation.max_tokens must be positive".to_string());
+        }
+        if self.resilience.base_delay > self.resilience.max_delay {
+            errors.push("resilience.base_delay_ms is greater than max_delay_ms".to_string());
+        }
+        if self.rate_limit.chat_burst == 0 || self.rate_limit.global_burst == 0 {
+            errors.push("rate_limit: burst must be positive".to_string());
+        }
+        if self.rate_limit.chat_per_minute <= 0.0 || self.rate_limit.global_per_minute <= 0.0 {
+            errors.push("rate_limit: per_minute must be positive".to_string());
+        }
+        if self.rate_limit.max_input_chars == 0 {
+            errors.push("rate_limit.max_input_chars must be positive".to_string());
+        }
+        let threshold = self.intent.llm_threshold;
+        if !(0.0..=1.0).contains(&threshold) {
+            errors.push(format!(
+                "intent.llm_threshold: {threshold} is outside 0..=1"
+            ));
+        }
+        let coverage = self.retrieval.min_coverage;
+        if !(0.0..=1.0).contains(&coverage) {
+            errors.push(format!(
+                "retrieval.min_coverage: {coverage} is outside 0..=1"
+            ));
+        }
+        match self.embeddings.provider.as_str() {
+            "none" | "yandex" => {}
+            other => errors.push(format!(
+                "embeddings.provider: unknown provider {other:?}, expected \"none\" or \"yandex\""
+            )),
+        }
+        if self.embeddings.top_k == 0 {
+            errors.push("embeddings.top_k must be positive".to_string());
+        }
+        let min_score = self.embeddings.min_score;
+        if !(-1.0..=1.0).contains(&min_score) {
+            errors.push(format!(
+                "embeddings.min_score: {min_score} is outside -1..=1"
+            ));
+        }
+        match self.verification.action.as_str() {
+            "off" | "log" | "append" | "rewrite" | "regenerate" => {}
+            other => errors.push(format!(
+                "verification.action: unknown action {other:?}, expected \"off\", \"log\", \"append\", \"rewrite\" or \"regenerate\""
+            )),
+        }
+
+        if !self.data.programs_dir.is_dir() {
+            errors.push(format!(
+                "data.programs_dir: {} is not a directory",
+                self.data.programs_dir.display()
+            ));
+        }
+        if let Err(prompt_errors) = PromptSet::load(&self.prompts.dir) {
+            errors.extend(
+                prompt_errors
+                    .into_iter()
+                    .map(|error| format!("prompts: {error}")),
+            );
+        }
+        if self.data.fields.is_empty() {
+            errors.push("data.fields is empty".to_string());
+        }
+        let unknown: Vec<&str> = self
+            .data
+            .fields
+            .iter()
+            .map(String::as_str)
+            .filter(|field| !PROGRAM_FIELDS.contains(field))
+            .collect();
+        if !unknown.is_empty() {
+            errors.push(format!(
+                "data.fields: unknown fields {}",
+                unknown.join(", ")
+            ));
+        }
+        errors
+    }
+}
+
+fn read_file(path: &Path) -> Result<String, ConfigError> {
+    std::fs::read_to_string(path)
+        .map_err(|e| ConfigError(vec![format!("{}: {}", path.display(), e)]))
+}
+
+fn env_var(name: &str) -> Option<String> {
+    dotenv::var(name).ok()
+}
+
+/// Чтение переменных окружения с накоплением ошибок разбора
+struct Env<'a, F> {
+    var: &'a F,
+    errors: &'a mut Vec<String>,
+}
+
+impl<F: Fn(&str) -> Option<String>> Env<'_, F> {
+    fn parse<T: FromStr>(&mut self, name: &str) -> Option<T>
+    where
+        T::Err: Display,
+    {
+        let value = (self.var)(name).filter(|v| !v.trim().is_empty())?;
+        match value.trim().parse() {
+            Ok(parsed) => Some(parsed),
+            Err(err) => {
+                self.errors.push(format!("{name}={value:?}: {err}"));
+                None
+            }
+        }
+    }
+
+    fn set<T: FromEnv>(&mut self, name: &str, target: &mut T) {
+        if let Some(value) = self.parse::<T::Value>(name) {
+            target.assign(value);
+        }
+    }
+
+    fn secs(&mut self, name: &str, target: &mut Duration) {
+        if let Some(secs) = self.parse(name) {
+            *target = Duration::from_secs(secs);
+        }
+    }
+
+    fn millis(&mut self, name: &str, target: &mut Duration) {
+        if let Some(millis) = self.parse(name) {
+            *target = Duration::from_millis(millis);
+        }
+    }
+
+    /// Список через запятую
+    fn list<T: FromStr>(&mut self, name: &str) -> Option<Vec<T>>
+    where
+        T::Err: Display,
+    {
+        let value: String = self.parse(name)?;
+        let mut items = Vec::new();
+        for item in value.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+            match item.parse() {
+                Ok(item) => items.push(item),
+                Err(err) => self.errors.push(format!("{name}: {item:?}: {err}")),
+            }
+        }
+        Some(items)
+    }
+}
+
+/// Поле, которое можно задать переменной окружения: значение или `Option` от него
+trait FromEnv {
+    type Value: FromStr<Err: Display>;
+
+    fn assign(&mut self, value: Self::Value);
+}
+
+macro_rules! from_env {
+    ($($ty:ty),*) => {$(
+        impl FromEnv for $ty {
+            type Value = $ty;
+
+            fn assign(&mut self, value: $ty) {
+                *self = value;
+            }
+        }
+
+        impl FromEnv for Option<$ty> {
+            type Value = $ty;
+
+            fn assign(&mut self, value: $ty) {
+                *self = Some(value);
+            }
+        }
+    )*};
+}
+
+from_env!(String, PathBuf, bool, u32, u64, usize, f32, f64);
+
+/// Длительность, записанная в файле целым числом секунд
+pub fn secs<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    u64::deserialize(deserializer).map(Duration::from_secs)
+}
+
+/// Длительность, записанная в файле целым числом миллисекунд
+pub fn millis<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+    u64::deserialize(deserializer).map(Duration::from_millis)
+}
+
+/// Квота, где 0 означает отсутствие ограничения
+pub fn zero_as_none<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    u32::deserialize(deserializer).map(|value| Some(value).filter(|&value| value > 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn cli() -> Cli {
+        Cli::parse_from(["tg_bot"])
+    }
+
+    fn vars(vars: &[(&str, &str)]) -> impl Fn(&str) -> Option<String> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        move |name| vars.get(name).cloned()
+    }
+
+    const SECRETS: &[(&str, &str)] = &[
+        ("TELOXIDE_TOKEN", "token"),
+        ("YANDEX_GPT_API_KEY", "key"),
+        ("YANDEX_FOLDER_ID", "folder"),
+    ];
+
+    #[test]
+    fn test_layers_override_in_order() {
+        let file = r#"
+            [llm]
+            model_version = "rc"
+
+            [generation]
+            temperature = 0.3
+            max_tokens = 2000
+
+            [rate_limit]
+            chat_burst = 2
+            daily_quota = 0
+
+            [cache]
+            ttl_secs = 60
+        "#;
+        let mut env = SECRETS.to_vec();
+        env.extend([("LLM_MAX_TOKENS", "1000"), ("RATE_LIMIT_CHAT_BURST", "")]);
+        let cli = Cli::parse_from(["tg_bot", "--temperature", "0.5"]);
+
+        let config = Config::from_layers(Some(file), vars(&env), &cli, true).unwrap();
+        assert_eq!(config.llm.model_version.as_deref(), Some("rc"));
+        assert_eq!(config.generation.temperature, 0.5);
+        assert_eq!(config.generation.max_tokens, 1000);
+        // Пустая переменная не перекрывает файл
+        assert_eq!(config.rate_limit.chat_burst, 2);
+        assert_eq!(config.rate_limit.daily_quota, None);
+        assert_eq!(config.cache.ttl, Duration::from_secs(60));
+        assert_eq!(config.dialogue.max_turns, 5);
+    }
+
+    #[test]
+    fn test_openai_model_only_for_openai() {
+        let mut env = SECRETS.to_vec();
+        env.push(("OPENAI_MODEL", "gpt-4o-mini"));
+        let config = Config::from_layers(None, vars(&env), &cli(), true).unwrap();
+        assert_eq!(config.llm.model, None);
+
+        env.extend([("LLM_PROVIDER", "openai"), ("OPENAI_API_KEY", "key")]);
+        let config = Config::from_layers(None, vars(&env), &cli(), true).unwrap();
+        assert_eq!(config.llm.model.as_deref(), Some("gpt-4o-mini"));
+    }
+
+    #[test]
+    fn test_reports_all_errors_at_once() {
+        let file = r#"
+            [llm]
+            provider = "openai"
+
+            [generation]
+            temperature = 3.0
+
+            [data]
+            fields = ["cost", "price"]
+        "#;
+        let env = vars(&[
+            ("TELOXIDE_TOKEN", "token"),
+            ("RATE_LIMIT_CHAT_BURST", "много"),
+            ("ADMIN_USER_IDS", "1, admin"),
+            ("EMBEDDINGS_PROVIDER", "yandex"),
+            ("VERIFICATION_ACTION", "fix"),
+        ]);
+        let cli = Cli::parse_from(["tg_bot", "--programs-dir", "missing"]);
+
+        let ConfigError(errors) = Config::from_layers(Some(file), env, &cli, true).unwrap_err();
+        assert_eq!(errors.len(), 10, "{errors:#?}");
+        for expected in [
+            "RATE_LIMIT_CHAT_BURST",
+            "ADMIN_USER_IDS",
+            // Векторы YandexGPT нужны даже с провайдером openai
+            "YANDEX_GPT_API_KEY",
+            "YANDEX_FOLDER_ID",
+            "OPENAI_API_KEY",
+            "LLM_MODEL",
+            "generation.temperature",
+            "data.programs_dir",
+            "unknown fields price",
+            "verification.action",
+        ] {
+            assert!(
+                errors.iter().any(|error| error.contains(expected)),
+                "no {expected} in {errors:#?}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_unknown_keys() {
+        let ConfigError(errors) =
+            Config::from_layers(Some("[llm]\nmodle = \"x\""), vars(SECRETS), &cli(), true)
+                .unwrap_err();
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].contains("modle"), "{errors:?}");
+    }
+}
@@ -0,0 +1,283 @@
+use std::fs;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Форма контроля по дисциплине
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlForm {
+    Exam,
+    Credit,
+    GradedCredit,
+}
+
+impl ControlForm {
+    /// Как форма контроля пишется в учебном плане, длинные написания раньше коротких
+    const LABELS: [(&'static str, ControlForm); 4] = [
+        ("дифференцированный зачет", ControlForm::GradedCredit),
+        ("зачет с оценкой", ControlForm::GradedCredit),
+        ("экзамен", ControlForm::Exam),
+        ("зачет", ControlForm::Credit),
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ControlForm::Exam => "экзамен",
+            ControlForm::Credit => "зачет",
+            ControlForm::GradedCredit => "дифференцированный зачет",
+        }
+    }
+}
+
+/// Раздел учебного плана, к которому относится дисциплина
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CourseBlock {
+    Mandatory,
+    Elective,
+    Practice,
+    FinalAttestation,
+}
+
+impl CourseBlock {
+    pub fn label(self) -> &'static str {
+        match self {
+            CourseBlock::Mandatory => "обязательная дисциплина",
+            CourseBlock::Elective => "дисциплина по выбору",
+            CourseBlock::Practice => "практика",
+            CourseBlock::FinalAttestation => "государственная итоговая аттестация",
+        }
+    }
+}
+
+/// Дисциплина учебного плана
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Discipline {
+    pub name: String,
+    pub semesters: Vec<u32>,
+    /// Трудоемкость в зачетных единицах
+    pub credits: u32,
+    pub hours: u32,
+    /// `None`, если в плане не указана
+    pub control: Option<ControlForm>,
+    pub block: CourseBlock,
+}
+
+impl Discipline {
+    /// Описание для промпта: "1, 2 семестр, 6 з.е., 216 ч., экзамен, обязательная дисциплина"
+    pub fn describe(&self) -> String {
+        let semesters: Vec<String> = self.semesters.iter().map(u32::to_string).collect();
+        let mut parts = vec![
+            format!("{} семестр", semesters.join(", ")),
+            format!("{} з.е.", self.credits),
+            format!("{} ч.", self.hours),
+        ];
+        if let Some(control) = self.control {
+            parts.push(control.label().to_string());
+        }
+        parts.push(self.block.label().to_string());
+        parts.join(", ")
+    }
+}
+
+/// Учебный план программы, `data/<name>_curriculum.json` от `parse_curriculum`
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Curriculum {
+    pub disciplines: Vec<Discipline>,
+}
+
+impl Curriculum {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_str(&json)
+            .map_err(|e| anyhow::anyhow!("Invalid curriculum {}: {}", path.display(), e))
+    }
+
+    /// Прочитать учебный план из pdf с сайта ИТМО, без сети
+    pub fn from_pdf(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let bytes = fs::read(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let text = pdf_extract::extract_text_from_mem(&bytes).map_err(|e| {
+            anyhow::anyhow!("Failed to extract text from {}: {}", path.display(), e)
+        })?;
+        let curriculum = Curriculum::parse(&text);
+        if curriculum.disciplines.is_empty() {
+            anyhow::bail!("No disciplines found in {}", path.display());
+        }
+        Ok(curriculum)
+    }
+
+    /// Разобрать текст учебного плана. Строка дисциплины выглядит как
+    /// "1, 2 Машинное обучение 6 216 Экзамен": семестры, название, з.е., часы
+    /// и необязательная форма контроля. Раздел берется из последнего заголовка
+    /// ("Обязательные дисциплины. 1 семестр", "Пул выборных дисциплин", "Блок 2. Практика").
+    /// Ячейки таблицы pdf иногда попадают на разные строки, поэтому строка
+    /// дисциплины может собираться из нескольких подряд.
+    pub fn parse(text: &str) -> Self {
+        let mut disciplines = Vec::new();
+        let mut block = CourseBlock::Mandatory;
+        let mut pending: Vec<String> = Vec::new();
+        for line in text.lines() {
+            let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+            if line.is_empty() {
+                continue;
+            }
+            if let Some(heading) = parse_heading(&line) {
+                block = heading;
+                pending.clear();
+                continue;
+            }
+            pending.push(line);
+            // Перед строкой дисциплины может оказаться шапка таблицы или колонтитул страницы
+            let row =
+                (0..pending.len()).find_map(|start| parse_row(&pending[start..].join(" "), block));
+            if let Some(discipline) = row {
+                disciplines.push(discipline);
+                pending.clear();
+            } else if pending.len() >= 8 {
+                pending.remove(0);
+            }
+        }
+        Curriculum { disciplines }
+    }
+
+    /// Названия дисциплин по порядку, ими заменяется `courses` из манифеста
+    pub fn course_names(&self) -> Vec<String> {
+        self.disciplines.iter().map(|d| d.name.clone()).collect()
+    }
+}
+
+/// Раздел плана по строке заголовка. Заголовки не начинаются с номера семестра,
+/// так что название дисциплины вроде "Практика применения МО" заголовком не считается.
+fn parse_heading(line: &str) -> Option<CourseBlock> {
+    if line.starts_with(|c: char| c.is_ascii_digit()) {
+        return None;
+    }
+    let line = line.to_lowercase().replace('ё', "е");
+    if line.contains("итоговая аттестация") || line.split_whitespace().any(|word| word == "гиа")
+    {
+        Some(CourseBlock::FinalAttestation)
+    } else if line.starts_with("блок") && line.contains("практик") {
+        Some(CourseBlock::Practice)
+    } else if line.contains("выборн") || line.contains("элективн") || line.contains("по выбору")
+    {
+        Some(CourseBlock::Elective)
+    } else if line.contains("обязательн") {
+        Some(CourseBlock::Mandatory)
+    } else {
+        None
+    }
+}
+
+/// Строка дисциплины: семестры, название, з.е., часы, форма контроля
+fn parse_row(line: &str, block: CourseBlock) -> Option<Discipline> {
+    let mut rest = line.trim();
+    let lower = rest.to_lowercase().replace('ё', "е");
+    let mut control = None;
+    for (label, form) in ControlForm::LABELS {
+        if lower.len() == rest.len() && lower.ends_with(label) {
+            control = Some(form);
+            rest = rest[..rest.len() - label.len()].trim_end();
+            break;
+        }
+    }
+
+    let mut tokens: Vec<&str> = rest.split_whitespace().collect();
+    let hours: u32 = tokens.pop()?.parse().ok()?;
+    let credits: u32 = tokens.pop()?.parse().ok()?;
+    if credits == 0 || hours < credits {
+        return None;
+    }
+    let mut semesters = Vec::new();
+    while let Some(token) = tokens.first() {
+        let numbers: Option<Vec<u32>> = token
+            .split(',')
+            .filter(|part| !part.is_empty())
+            .map(|part| part.parse().ok().filter(|s| (1..=12).contains(s)))
+            .collect();
+        match numbers {
+            Some(numbers) if !numbers.is_empty() => {
+                semesters.extend(numbers);
+                tokens.remove(0);
+            }
+            _ => break,
+        }
+    }
+    // "1 семестр 18 648" - итог раздела, заголовок которого оказался на другой строке
+    if semesters.is_empty() || tokens.is_empty() || tokens[0].starts_with("семестр") {
+        return None;
+    }
+    Some(Discipline {
+        name: tokens.join(" "),
+        semesters,
+        credits,
+        hours,
+        control,
+        block,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_curriculum_text() {
+        let text = "\
+Учебный план
+Семестры Наименование модулей/дисциплин Трудоемкость Часы
+Блок 1. Модули (дисциплины) 72 2592
+Обязательные дисциплины.
+1 семестр 12 432
+1 Воркшоп по созданию продукта на данных / Data Product Development Workshop 3 108 Зачет
+Страница 1 из 3
+1 Машинное обучение 6 216 Экзамен
+Пул выборных дисциплин. 1 семестр 18 648
+1, 2 Глубокое обучение 3 108 Дифференцированный зачет
+2
+Технологии обработки
+естественного языка
+3
+108
+Блок 2. Практика 45 1620
+3 Производственная практика 21 756
+Блок 3. Государственная итоговая аттестация 6 216
+4 Подготовка к защите и защита ВКР 6 216
+";
+        let curriculum = Curriculum::parse(text);
+        let names = curriculum.course_names();
+        assert_eq!(
+            names,
+            vec![
+                "Воркшоп по созданию продукта на данных / Data Product Development Workshop",
+                "Машинное обучение",
+                "Глубокое обучение",
+                "Технологии обработки естественного языка",
+                "Производственная практика",
+                "Подготовка к защите и защита ВКР",
+            ]
+        );
+        let ml = &curriculum.disciplines[1];
+        assert_eq!(ml.credits, 6);
+        assert_eq!(ml.hours, 216);
+        assert_eq!(ml.control, Some(ControlForm::Exam));
+        assert_eq!(ml.block, CourseBlock::Mandatory);
+        let dl = &curriculum.disciplines[2];
+        assert_eq!(dl.semesters, vec![1, 2]);
+        assert_eq!(dl.block, CourseBlock::Elective);
+        assert_eq!(
+            dl.describe(),
+            "1, 2 семестр, 3 з.е., 108 ч., дифференцированный зачет, дисциплина по выбору"
+        );
+        assert_eq!(curriculum.disciplines[3].control, None);
+        assert_eq!(curriculum.disciplines[4].block, CourseBlock::Practice);
+        assert_eq!(
+            curriculum.disciplines[5].block,
+            CourseBlock::FinalAttestation
+        );
+    }
+}
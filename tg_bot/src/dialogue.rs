@@ -0,0 +1,131 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+use teloxide::types::ChatId;
+
+use crate::llm_provider::ChatMessage;
+
+/// Один обмен репликами: вопрос пользователя и ответ бота
+#[derive(Debug, Clone)]
+pub struct Turn {
+    pub question: String,
+    pub answer: String,
+}
+
+impl Turn {
+    fn estimated_tokens(&self) -> usize {
+        estimate_tokens(&self.question) + estimate_tokens(&self.answer)
+    }
+}
+
+/// История диалогов по чатам. Для каждого чата хранится не больше
+/// `max_turns` последних обменов и не больше `max_tokens` токенов (по оценке).
+#[derive(Debug)]
+pub struct DialogueMemory {
+    max_turns: usize,
+    max_tokens: usize,
+    chats: Mutex<HashMap<ChatId, VecDeque<Turn>>>,
+}
+
+impl DialogueMemory {
+    pub fn new(max_turns: usize, max_tokens: usize) -> Self {
+        DialogueMemory {
+            max_turns,
+            max_tokens,
+            chats: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Предыдущие реплики чата в виде сообщений user/assistant, от старых к новым
+    pub fn history(&self, chat_id: ChatId) -> Vec<ChatMessage> {
+        let chats = self.chats.lock().unwrap();
+        chats
+            .get(&chat_id)
+            .map(|turns| {
+                turns
+                    .iter()
+                    .flat_map(|turn| {
+                        [
+                            ChatMessage::user(turn.question.clone()),
+                            ChatMessage::assistant(turn.answer.clone()),
+                        ]
+                    })
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn remember(&self, chat_id: ChatId, question: &str, answer: &str) {
+        let mut chats = self.chats.lock().unwrap();
+        let turns = chats.entry(chat_id).or_default();
+        turns.push_back(Turn {
+            question: question.to_string(),
+            answer: answer.to_string(),
+        });
+
+        while turns.len() > self.max_turns {
+            turns.pop_front();
+        }
+        let mut total: usize = turns.iter().map(Turn::estimated_tokens).sum();
+        while total > self.max_tokens {
+            match turns.pop_front() {
+                Some(turn) => total -= turn.estimated_tokens(),
+                None => break,
+            }
+        }
+    }
+
+    pub fn reset(&self, chat_id: ChatId) {
+        self.chats.lock().unwrap().remove(&chat_id);
+    }
+}
+
+/// Грубая оценка числа токенов: для русского текста около 3 символов на токен
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(3)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_provider::ChatRole;
+
+    #[test]
+    fn test_history_is_bounded_by_turns() {
+        let memory = DialogueMemory::new(2, 10_000);
+        let chat = ChatId(1);
+        memory.remember(chat, "q1", "a1");
+        memory.remember(chat, "q2", "a2");
+        memory.remember(chat, "q3", "a3");
+
+        let history = memory.history(chat);
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[0].text, "q2");
+        assert_eq!(history[0].role, ChatRole::User);
+        assert_eq!(history[3].text, "a3");
+        assert_eq!(history[3].role, ChatRole::Assistant);
+    }
+
+    #[test]
+    fn test_history_is_bounded_by_tokens() {
+        let memory = DialogueMemory::new(10, 10);
+        let chat = ChatId(1);
+        memory.remember(chat, "вопрос", "ответ");
+        memory.remember(chat, "второй вопрос", "второй ответ");
+
+        let history = memory.history(chat);
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].text, "второй вопрос");
+    }
+
+    #[test]
+    fn test_reset_clears_only_one_chat() {
+        let memory = DialogueMemory::new(5, 1000);
+        memory.remember(ChatId(1), "q", "a");
+        memory.remember(ChatId(2), "q", "a");
+        memory.reset(ChatId(1));
+
+        assert!(memory.history(ChatId(1)).is_empty());
+        assert_eq!(memory.history(ChatId(2)).len(), 2);
+    }
+}
@@ -0,0 +1,185 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{EmbeddingsConfig, LlmConfig};
+use crate::http_client::HttpClientConfig;
+use crate::llm_error::LlmError;
+
+/// Что превращается в вектор: у YandexGPT для фрагментов базы знаний
+/// и для вопросов разные модели
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmbeddingKind {
+    Document,
+    Query,
+}
+
+#[derive(Debug, Clone)]
+pub struct Embedding {
+    pub vector: Vec<f32>,
+    pub tokens: u64,
+}
+
+/// Провайдер векторных представлений текста для семантического поиска
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Короткое имя провайдера для логов
+    fn name(&self) -> &str;
+
+    /// Модель для фрагментов: векторы разных моделей несравнимы,
+    /// поэтому она сохраняется вместе с индексом
+    fn model(&self) -> &str;
+
+    async fn embed(&self, text: &str, kind: EmbeddingKind) -> Result<Embedding, LlmError>;
+}
+
+/// Text embeddings Yandex Foundation Models
+#[derive(Debug, Clone)]
+pub struct YandexEmbeddings {
+    http: reqwest::Client,
+    api_key: String,
+    base_url: String,
+    folder_id: String,
+    doc_model: String,
+    query_model: String,
+}
+
+impl YandexEmbeddings {
+    pub fn new(
+        http: reqwest::Client,
+        api_key: String,
+        base_url: String,
+        folder_id: String,
+        doc_model: String,
+        query_model: String,
+    ) -> Self {
+        YandexEmbeddings {
+            http,
+            api_key,
+            base_url,
+            folder_id,
+            doc_model,
+            query_model,
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for YandexEmbeddings {
+    fn name(&self) -> &str {
+        "yandex"
+    }
+
+    fn model(&self) -> &str {
+        &self.doc_model
+    }
+
+    async fn embed(&self, text: &str, kind: EmbeddingKind) -> Result<Embedding, LlmError> {
+        let model = match kind {
+            EmbeddingKind::Document => &self.doc_model,
+            EmbeddingKind::Query => &self.query_model,
+        };
+        let request = EmbeddingRequest {
+            model_uri: format!("emb://{}/{}/latest", self.folder_id, model),
+            text: text.to_string(),
+        };
+        let response = self
+            .http
+            .post(&self.base_url)
+            .header("Authorization", format!("Api-Key {}", self.api_key))
+            .json(&request)
+            .send()
+            .await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(LlmError::from_status(status, &headers, body));
+        }
+
+        let response: EmbeddingResponse =
+            serde_json::from_str(&body).map_err(|err| LlmError::malformed(err, &body))?;
+        if response.embedding.is_empty() {
+            return Err(LlmError::malformed("empty embedding", body));
+        }
+        Ok(Embedding {
+            vector: response.embedding,
+            tokens: response.num_tokens.parse().unwrap_or(0),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingRequest {
+    pub model_uri: String,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EmbeddingResponse {
+    pub embedding: Vec<f32>,
+    // Как и у completion, счетчик строкой
+    pub num_tokens: String,
+    pub model_version: String,
+}
+
+/// Создать провайдера по настройкам `[embeddings]`, `None` - семантический поиск выключен.
+/// Ключ и каталог берутся из `[llm.yandex]`.
+pub fn embeddings_from_config(
+    config: &EmbeddingsConfig,
+    llm: &LlmConfig,
+    http: &HttpClientConfig,
+) -> anyhow::Result<Option<Arc<dyn EmbeddingProvider>>> {
+    let required = |value: &Option<String>, name: &str| {
+        value
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("{name} is not set"))
+    };
+    Ok(match config.provider.as_str() {
+        "none" => None,
+        "yandex" => Some(Arc::new(YandexEmbeddings::new(
+            http.build()?,
+            required(&llm.yandex.api_key, "llm.yandex.api_key")?,
+            config.api_url.clone(),
+            required(&llm.yandex.folder_id, "llm.yandex.folder_id")?,
+            config.doc_model.clone(),
+            config.query_model.clone(),
+        ))),
+        other => anyhow::bail!("Unknown embeddings provider: {other}"),
+    })
+}
+
+/// Косинусная близость, 0 для векторов разной длины или нулевых
+pub fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let (dot, norm_a, norm_b) = a
+        .iter()
+        .zip(b)
+        .fold((0.0, 0.0, 0.0), |(dot, na, nb), (x, y)| {
+            (dot + x * y, na + x * x, nb + y * y)
+        });
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a.sqrt() * norm_b.sqrt())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine() {
+        assert!((cosine(&[1.0, 0.0], &[2.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine(&[1.0, 0.0], &[0.0, 3.0]).abs() < 1e-6);
+        assert!((cosine(&[1.0, 1.0], &[-1.0, -1.0]) + 1.0).abs() < 1e-6);
+        assert_eq!(cosine(&[1.0], &[1.0, 0.0]), 0.0);
+        assert_eq!(cosine(&[0.0, 0.0], &[1.0, 0.0]), 0.0);
+    }
+}
@@ -0,0 +1,122 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use clap::Parser;
+use tokio::sync::watch;
+
+use tg_bot::answer_cache::{AnswerCache, CacheConfig};
+use tg_bot::billing::PriceList;
+use tg_bot::config::{Cli, Config};
+use tg_bot::evaluation::{RunReport, Suite, Summary};
+use tg_bot::intent::IntentClassifier;
+use tg_bot::llm_provider::{self, LlmProvider};
+use tg_bot::mock_llm::MockYandexGpt;
+use tg_bot::pipeline::{AnswerContext, AnswerTrace, call_model, get_answer_from_llm};
+use tg_bot::programs::ProgramRegistry;
+use tg_bot::prompts::PromptSet;
+use tg_bot::resilience::ResilientProvider;
+use tg_bot::vector_index::SemanticSearch;
+
+/// Прогнать набор вопросов через пайплайн ответа и оценить ответы
+#[derive(Debug, Parser)]
+struct Args {
+    /// Набор вопросов в YAML или JSONL
+    #[arg(long, default_value = "eval/suite.yaml")]
+    suite: PathBuf,
+    /// Куда сохранить результаты прогона в JSON
+    #[arg(long)]
+    output: Option<PathBuf>,
+    /// Результаты предыдущего прогона для сравнения
+    #[arg(long)]
+    baseline: Option<PathBuf>,
+    /// Куда сохранить отчет в markdown, по умолчанию выводится в stdout
+    #[arg(long)]
+    report: Option<PathBuf>,
+    #[command(flatten)]
+    bot: Cli,
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    pretty_env_logger::init();
+    dotenv::dotenv().ok();
+    let args = Args::parse();
+    let mut config = Config::load_without_telegram(&args.bot)?;
+    if args.bot.check_config {
+        println!("Configuration is valid");
+        return Ok(());
+    }
+    let _mock_llm = if args.bot.mock_llm {
+        let server = MockYandexGpt::start().await?;
+        config.llm.yandex.api_url = server.url();
+        Some(server)
+    } else {
+        None
+    };
+    let suite = Suite::load(&args.suite)?;
+    let baseline = args.baseline.as_ref().map(RunReport::load).transpose()?;
+
+    let data = ProgramRegistry::load(&config.data.programs_dir)?;
+    let prices = PriceList::parse(&config.llm.prices)?;
+    let llm: Arc<dyn LlmProvider> = Arc::new(ResilientProvider::new(
+        llm_provider::provider_from_config(&config.llm, &config.http)?,
+        config.resilience.clone(),
+    ));
+    // Каждый вопрос отвечается заново, иначе повторы в наборе искажают стоимость
+    let cache = AnswerCache::new(CacheConfig {
+        ttl: Duration::ZERO,
+        ..config.cache.clone()
+    });
+    let mut classifier = IntentClassifier::new(data.aliases());
+    if config.intent.llm_fallback {
+        classifier = classifier.with_llm_fallback(config.intent.llm_threshold);
+    }
+    let prompts = PromptSet::load(&config.prompts.dir)
+        .map_err(|errors| anyhow::anyhow!("Invalid prompt templates: {}", errors.join("; ")))?;
+    let semantic = SemanticSearch::from_config(&config, &data).await;
+    let context = AnswerContext {
+        data: &data,
+        llm: llm.as_ref(),
+        cache: &cache,
+        classifier: &classifier,
+        config: &config,
+        prompts: &prompts,
+        semantic: semantic.as_ref(),
+    };
+
+    let mut results = Vec::new();
+    for case in &suite.cases {
+        log::info!("Evaluating {}: {}", case.id, case.question);
+        let (partial, _) = watch::channel(String::new());
+        let mut trace = AnswerTrace::default();
+        let started = Instant::now();
+        let answer = get_answer_from_llm(&case.question, &[], &context, &partial, &mut trace).await;
+        let latency_ms = started.elapsed().as_millis() as u64;
+        let cost = trace.calls.iter().fold(0.0, |sum, (purpose, usage)| {
+            sum + prices.cost(call_model(purpose, llm.as_ref(), &config), usage)
+        });
+        results.push(suite.score(case, &answer, &trace, cost, latency_ms));
+    }
+
+    let run = RunReport {
+        provider: llm.name().to_string(),
+        model: llm.model().to_string(),
+        prompt_version: prompts.version().to_string(),
+        summary: Summary::of(&results),
+        cases: results,
+    };
+    if let Some(output) = &args.output {
+        if let Some(dir) = output.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(output, serde_json::to_string_pretty(&run)?)?;
+        log::info!("Saved results to {}", output.display());
+    }
+    let report = run.to_markdown(baseline.as_ref());
+    match &args.report {
+        Some(path) => std::fs::write(path, report)?,
+        None => print!("{report}"),
+    }
+    Ok(())
+}
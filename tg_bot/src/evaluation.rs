@@ -0,0 +1,434 @@
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::pipeline::AnswerTrace;
+
+/// Фразы, по которым ответ считается отказом отвечать
+const DEFAULT_REFUSAL_MARKERS: &[&str] = &[
+    "не могу ответить",
+    "могу отвечать только",
+    "не относится к магистратур",
+];
+
+/// Набор вопросов для оценки качества ответов
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Suite {
+    #[serde(default)]
+    pub refusal_markers: Vec<String>,
+    pub cases: Vec<Case>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Case {
+    pub id: String,
+    pub question: String,
+    /// Программа, к которой должен быть отнесен вопрос. Не задана - маршрутизация не проверяется.
+    #[serde(default)]
+    pub program: Option<String>,
+    /// Факты, которые должны быть в ответе
+    #[serde(default)]
+    pub facts: Vec<Fact>,
+    /// Бот должен отказаться отвечать
+    #[serde(default)]
+    pub refusal: bool,
+}
+
+/// Факт: строка или список допустимых написаний (`["599 000", "599000"]`)
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Fact {
+    One(String),
+    AnyOf(Vec<String>),
+}
+
+impl Fact {
+    fn variants(&self) -> &[String] {
+        match self {
+            Fact::One(fact) => std::slice::from_ref(fact),
+            Fact::AnyOf(variants) => variants,
+        }
+    }
+
+    fn found_in(&self, answer: &str) -> bool {
+        let answer = normalize(answer);
+        self.variants()
+            .iter()
+            .any(|variant| answer.contains(&normalize(variant)))
+    }
+}
+
+impl std::fmt::Display for Fact {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.variants().join(" | "))
+    }
+}
+
+/// Нижний регистр, `ё` как `е`, без пробелов: "599 000 ₽" совпадает с "599000₽"
+fn normalize(text: &str) -> String {
+    text.to_lowercase()
+        .replace('ё', "е")
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect()
+}
+
+impl Suite {
+    /// Загрузить набор из YAML или JSONL (по одному `Case` на строку)
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        let suite = if path.extension().is_some_and(|ext| ext == "jsonl") {
+            let cases = text
+                .lines()
+                .enumerate()
+                .filter(|(_, line)| !line.trim().is_empty())
+                .map(|(index, line)| {
+                    serde_json::from_str(line)
+                        .map_err(|e| anyhow::anyhow!("{}:{}: {}", path.display(), index + 1, e))
+                })
+                .collect::<anyhow::Result<_>>()?;
+            Suite {
+                refusal_markers: Vec::new(),
+                cases,
+            }
+        } else {
+            serde_yaml::from_str(&text)
+                .map_err(|e| anyhow::anyhow!("Invalid suite {}: {}", path.display(), e))?
+        };
+        if suite.cases.is_empty() {
+            anyhow::bail!("No cases in {}", path.display());
+        }
+        Ok(suite)
+    }
+
+    fn refused(&self, answer: &str, trace: &AnswerTrace) -> bool {
+        if trace.intent == Some("off_topic") {
+            return true;
+        }
+        let answer = answer.to_lowercase();
+        let matches = |marker: &str| answer.contains(&marker.to_lowercase());
+        if self.refusal_markers.is_empty() {
+            DEFAULT_REFUSAL_MARKERS.iter().any(|marker| matches(marker))
+        } else {
+            self.refusal_markers.iter().any(|marker| matches(marker))
+        }
+    }
+
+    /// Оценить ответ на один вопрос
+    pub fn score(
+        &self,
+        case: &Case,
+        answer: &str,
+        trace: &AnswerTrace,
+        cost: f64,
+        latency_ms: u64,
+    ) -> CaseResult {
+        let missing_facts: Vec<String> = case
+            .facts
+            .iter()
+            .filter(|fact| !fact.found_in(answer))
+            .map(Fact::to_string)
+            .collect();
+        let refused = self.refused(answer, trace);
+        CaseResult {
+            id: case.id.clone(),
+            question: case.question.clone(),
+            answer: answer.to_string(),
+            program: trace.program.clone(),
+            routing_correct: case
+                .program
+                .as_ref()
+                .map(|expected| trace.program.as_ref() == Some(expected)),
+            facts_total: case.facts.len(),
+            facts_found: case.facts.len() - missing_facts.len(),
+            missing_facts,
+            refused,
+            refusal_correct: refused == case.refusal,
+            total_tokens: trace.usage.total_tokens,
+            cost,
+            latency_ms,
+            error: trace.error.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CaseResult {
+    pub id: String,
+    pub question: String,
+    pub answer: String,
+    pub program: Option<String>,
+    /// `None`, если в вопросе не указана ожидаемая программа
+    pub routing_correct: Option<bool>,
+    pub facts_total: usize,
+    pub facts_found: usize,
+    pub missing_facts: Vec<String>,
+    pub refused: bool,
+    pub refusal_correct: bool,
+    pub total_tokens: u64,
+    pub cost: f64,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Summary {
+    pub cases: usize,
+    /// Доля найденных фактов по всем вопросам
+    pub fact_recall: f64,
+    pub routing_accuracy: f64,
+    pub refusal_accuracy: f64,
+    pub total_tokens: u64,
+    pub cost: f64,
+    pub errors: usize,
+}
+
+impl Summary {
+    pub fn of(results: &[CaseResult]) -> Self {
+        let ratio = |part: usize, total: usize| {
+            if total == 0 {
+                1.0
+            } else {
+                part as f64 / total as f64
+            }
+        };
+        let routed: Vec<bool> = results.iter().filter_map(|r| r.routing_correct).collect();
+        Summary {
+            cases: results.len(),
+            fact_recall: ratio(
+                results.iter().map(|r| r.facts_found).sum(),
+                results.iter().map(|r| r.facts_total).sum(),
+            ),
+            routing_accuracy: ratio(routed.iter().filter(|&&ok| ok).count(), routed.len()),
+            refusal_accuracy: ratio(
+                results.iter().filter(|r| r.refusal_correct).count(),
+                results.len(),
+            ),
+            total_tokens: results.iter().map(|r| r.total_tokens).sum(),
+            cost: results.iter().fold(0.0, |sum, r| sum + r.cost),
+            errors: results.iter().filter(|r| r.error.is_some()).count(),
+        }
+    }
+}
+
+/// Метрики отчета: название, значение и формат
+type Metric = (&'static str, fn(&Summary) -> f64, fn(f64) -> String);
+
+const METRICS: [Metric; 6] = [
+    ("Полнота фактов", |s| s.fact_recall, percent),
+    ("Маршрутизация", |s| s.routing_accuracy, percent),
+    ("Отказы", |s| s.refusal_accuracy, percent),
+    ("Токены", |s| s.total_tokens as f64, |v| format!("{v:.0}")),
+    ("Стоимость, ₽", |s| s.cost, |v| format!("{v:.2}")),
+    ("Ошибки", |s| s.errors as f64, |v| format!("{v:.0}")),
+];
+
+fn percent(value: f64) -> String {
+    format!("{:.1}%", value * 100.0)
+}
+
+/// Результат прогона набора, сохраняется в JSON для сравнения с другими прогонами
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunReport {
+    pub provider: String,
+    pub model: String,
+    pub prompt_version: String,
+    pub summary: Summary,
+    pub cases: Vec<CaseResult>,
+}
+
+impl RunReport {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Ok(serde_json::from_str(&text)?)
+    }
+
+    /// Отчет в markdown: метрики прогона и, если есть, разница с `baseline`
+    pub fn to_markdown(&self, baseline: Option<&RunReport>) -> String {
+        let mut report = format!(
+            "# Оценка ответов\n\nМодель: {} ({}), промпты: {}\n\n",
+            self.model, self.provider, self.prompt_version
+        );
+        match baseline {
+            Some(baseline) => {
+                report.push_str(&format!(
+                    "Сравнение с {} ({}), промпты: {}\n\n| Метрика | Было | Стало | Разница |\n|---|---|---|---|\n",
+                    baseline.model, baseline.provider, baseline.prompt_version
+                ));
+                for (name, metric, format) in METRICS {
+                    let (before, after) = (metric(&baseline.summary), metric(&self.summary));
+                    let delta = after - before;
+                    let sign = if delta > 0.0 { "+" } else { "" };
+                    report.push_str(&format!(
+                        "| {} | {} | {} | {}{} |\n",
+                        name,
+                        format(before),
+                        format(after),
+                        sign,
+                        format(delta)
+                    ));
+                }
+            }
+            None => {
+                report.push_str("| Метрика | Значение |\n|---|---|\n");
+                for (name, metric, format) in METRICS {
+                    report.push_str(&format!(
+                        "| {} | {} |\n",
+                        name,
+                        format(metric(&self.summary))
+                    ));
+                }
+            }
+        }
+        report.push_str(&format!("\nВопросов: {}\n", self.summary.cases));
+
+        let mut failures = Vec::new();
+        for case in &self.cases {
+            let mut problems = Vec::new();
+            if !case.missing_facts.is_empty() {
+                problems.push(format!("нет фактов: {}", case.missing_facts.join(", ")));
+            }
+            if case.routing_correct == Some(false) {
+                problems.push(format!(
+                    "программа {}",
+                    case.program.as_deref().unwrap_or("не определена")
+                ));
+            }
+            if !case.refusal_correct {
+                problems.push(if case.refused {
+                    "лишний отказ".to_string()
+                } else {
+                    "нет отказа".to_string()
+                });
+            }
+            if let Some(error) = &case.error {
+                problems.push(format!("ошибка: {error}"));
+            }
+            if problems.is_empty() {
+                continue;
+            }
+            let was = baseline
+                .and_then(|b| b.cases.iter().find(|c| c.id == case.id))
+                .map(|before| {
+                    if before.facts_found > case.facts_found
+                        || (before.routing_correct == Some(true)
+                            && case.routing_correct == Some(false))
+                        || (before.refusal_correct && !case.refusal_correct)
+                    {
+                        " (регрессия)"
+                    } else {
+                        ""
+                    }
+                })
+                .unwrap_or_default();
+            failures.push(format!("- `{}`{}: {}", case.id, was, problems.join("; ")));
+        }
+        if !failures.is_empty() {
+            report.push_str(&format!("\n## Проблемы\n\n{}\n", failures.join("\n")));
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn suite() -> Suite {
+        serde_yaml::from_str(
+            r#"
+            cases:
+              - id: ai_cost
+                question: Сколько стоит AI?
+                program: ai
+                facts: [["599 000", "599 тысяч"], "₽"]
+              - id: weather
+                question: Какая погода?
+                refusal: true
+            "#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_score_case() {
+        let suite = suite();
+        let trace = AnswerTrace {
+            program: Some("ai".to_string()),
+            ..AnswerTrace::default()
+        };
+        let result = suite.score(
+            &suite.cases[0],
+            "Обучение стоит 599000 рублей",
+            &trace,
+            0.5,
+            10,
+        );
+        assert_eq!(result.routing_correct, Some(true));
+        assert_eq!(result.facts_found, 1);
+        assert_eq!(result.missing_facts, vec!["₽"]);
+        assert!(result.refusal_correct);
+
+        let off_topic = AnswerTrace {
+            intent: Some("off_topic"),
+            ..AnswerTrace::default()
+        };
+        let result = suite.score(
+            &suite.cases[1],
+            "Могу отвечать только по магистратурам",
+            &off_topic,
+            0.0,
+            1,
+        );
+        assert!(result.refused && result.refusal_correct);
+        assert_eq!(result.routing_correct, None);
+    }
+
+    #[test]
+    fn test_summary_and_comparison() {
+        let suite = suite();
+        let run = |answer: &str, program: &str| {
+            let trace = AnswerTrace {
+                program: Some(program.to_string()),
+                ..AnswerTrace::default()
+            };
+            let cases = vec![
+                suite.score(&suite.cases[0], answer, &trace, 1.0, 10),
+                suite.score(
+                    &suite.cases[1],
+                    "Не могу ответить",
+                    &AnswerTrace::default(),
+                    0.0,
+                    1,
+                ),
+            ];
+            RunReport {
+                provider: "yandex".to_string(),
+                model: "yandexgpt".to_string(),
+                prompt_version: "1".to_string(),
+                summary: Summary::of(&cases),
+                cases,
+            }
+        };
+        let baseline = run("599 000 ₽", "ai");
+        assert_eq!(baseline.summary.fact_recall, 1.0);
+        assert_eq!(baseline.summary.routing_accuracy, 1.0);
+        assert_eq!(baseline.summary.refusal_accuracy, 1.0);
+
+        let current = run("дорого", "ai_product");
+        assert_eq!(current.summary.fact_recall, 0.0);
+        let report = current.to_markdown(Some(&baseline));
+        assert!(
+            report.contains("| Полнота фактов | 100.0% | 0.0% | -100.0% |"),
+            "{report}"
+        );
+        assert!(report.contains("`ai_cost` (регрессия)"), "{report}");
+        assert!(!report.contains("`weather`"), "{report}");
+    }
+}
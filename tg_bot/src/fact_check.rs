@@ -0,0 +1,359 @@
+use std::collections::HashSet;
+
+use crate::citations;
+
+/// Вид значения, которое модель могла выдумать
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FactKind {
+    Number,
+    Money,
+    Date,
+    Email,
+}
+
+/// Значение из текста
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fact {
+    pub kind: FactKind,
+    /// Как значение написано в тексте: "599 000 ₽", "12.08.2025"
+    pub text: String,
+    /// Значение для сравнения: число без разделителей разрядов (с учетом "тыс."
+    /// и "млн"), дата `дд.мм` или `дд.мм.гггг`, адрес в нижнем регистре
+    pub value: String,
+}
+
+/// Валюта после суммы
+const CURRENCIES: &[&str] = &["₽", "руб", "р.", "rub"];
+
+/// Разделители разрядов: пробел, неразрывный и узкий неразрывный пробелы
+fn is_group_separator(c: char) -> bool {
+    matches!(c, ' ' | '\u{a0}' | '\u{202f}')
+}
+
+/// Адреса почты из текста и текст без них и без ссылок, чтобы цифры
+/// в адресах не принимались за числа
+fn extract_emails(text: &str) -> (Vec<Fact>, String) {
+    let mut emails = Vec::new();
+    let mut rest = String::with_capacity(text.len());
+    for word in text.split_inclusive(char::is_whitespace) {
+        let trimmed = word.trim_matches(|c: char| !(c.is_alphanumeric() || c == '@'));
+        let is_email = trimmed
+            .split_once('@')
+            .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.'));
+        if is_email {
+            emails.push(Fact {
+                kind: FactKind::Email,
+                text: trimmed.to_string(),
+                value: trimmed.to_lowercase(),
+            });
+            rest.push(' ');
+        } else if trimmed.starts_with("http") {
+            rest.push(' ');
+        } else {
+            rest.push_str(word);
+        }
+    }
+    (emails, rest)
+}
+
+/// Дата `д.мм`, `дд.мм` или `дд.мм.гггг` в форме `дд.мм[.гггг]`.
+/// Дроби вроде "3.14" и "2.50" датами не считаются: день 1..=31, месяц 1..=12.
+fn parse_date(raw: &str) -> Option<String> {
+    let parts: Vec<&str> = raw.split('.').collect();
+    let digits = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_digit());
+    let in_range = |part: &str, max: u32| part.parse().is_ok_and(|n: u32| (1..=max).contains(&n));
+    match parts.as_slice() {
+        [day, month] | [day, month, _]
+            if digits(day)
+                && day.len() <= 2
+                && in_range(day, 31)
+                && digits(month)
+                && month.len() == 2
+                && in_range(month, 12) =>
+        {
+            let mut date = format!("{day:0>2}.{month}");
+            if let [_, _, year] = parts.as_slice() {
+                if !(digits(year) && year.len() == 4) {
+                    return None;
+                }
+                date.push('.');
+                date.push_str(year);
+            }
+            Some(date)
+        }
+        _ => None,
+    }
+}
+
+/// Число `raw` с учетом слов после него в `after`: "тыс.", "млн", "₽".
+/// Возвращает вид значения, число и длину подписи в `after`, чтобы показать сумму целиком.
+fn parse_amount(raw: &str, after: &str) -> Option<(FactKind, f64, usize)> {
+    let number: String = raw
+        .chars()
+        .filter(|c| !is_group_separator(*c))
+        .map(|c| if c == ',' { '.' } else { c })
+        .collect();
+    let mut number: f64 = number.parse().ok()?;
+    let tail = after.to_lowercase();
+    let mut rest = tail.trim_start();
+    for (word, multiplier) in [("тыс", 1e3), ("млн", 1e6)] {
+        if let Some(stripped) = rest.strip_prefix(word) {
+            number *= multiplier;
+            rest = stripped
+                .trim_start_matches(|c: char| c.is_alphabetic() || c == '.')
+                .trim_start();
+        }
+    }
+    match CURRENCIES
+        .iter()
+        .find(|currency| rest.starts_with(*currency))
+    {
+        Some(currency) => Some((
+            FactKind::Money,
+            number,
+            tail.len() - rest.len() + currency.len(),
+        )),
+        None => Some((FactKind::Number, number, 0)),
+    }
+}
+
+fn format_number(number: f64) -> String {
+    if number.fract() == 0.0 {
+        format!("{}", number as u64)
+    } else {
+        format!("{number}")
+    }
+}
+
+/// Числа, суммы, даты и адреса почты из текста. Ссылки на фрагменты `[1]`,
+/// номера пунктов списка и цифры внутри слов ("GPT-4o", "3D") не считаются.
+pub fn extract(text: &str) -> Vec<Fact> {
+    let (mut facts, text) = extract_emails(&citations::strip_markers(text));
+    let chars: Vec<(usize, char)> = text.char_indices().collect();
+    let digit_at = |i: usize| chars.get(i).is_some_and(|(_, c)| c.is_ascii_digit());
+    let byte_at = |i: usize| chars.get(i).map_or(text.len(), |(byte, _)| *byte);
+    let mut i = 0;
+    while i < chars.len() {
+        let glued = i > 0 && chars[i - 1].1.is_alphanumeric();
+        if !digit_at(i) || glued {
+            i += 1;
+            continue;
+        }
+        let start = i;
+        loop {
+            while digit_at(i) {
+                i += 1;
+            }
+            let separator = chars.get(i).map(|(_, c)| *c);
+            let group = separator.is_some_and(is_group_separator)
+                && (1..=3).all(|k| digit_at(i + k))
+                && !digit_at(i + 4);
+            let fraction = matches!(separator, Some('.' | ',')) && digit_at(i + 1);
+            if group || fraction {
+                i += 1;
+            } else {
+                break;
+            }
+        }
+        if chars.get(i).is_some_and(|(_, c)| c.is_alphabetic()) {
+            continue;
+        }
+        let raw = &text[byte_at(start)..byte_at(i)];
+        // Номер пункта списка: "1. Подайте заявление"
+        let line_start = text[..byte_at(start)]
+            .rsplit('\n')
+            .next()
+            .is_some_and(|prefix| prefix.trim().is_empty());
+        let after = &text[byte_at(i)..];
+        let list_number = raw.chars().all(|c| c.is_ascii_digit())
+            && (after.starts_with(". ") || after.starts_with(") "));
+        if line_start && list_number {
+            continue;
+        }
+        if let Some(date) = parse_date(raw) {
+            facts.push(Fact {
+                kind: FactKind::Date,
+                text: raw.to_string(),
+                value: date,
+            });
+        } else if let Some((kind, number, consumed)) = parse_amount(raw, after) {
+            let label = after.get(..consumed).unwrap_or_default();
+            facts.push(Fact {
+                kind,
+                text: format!("{raw}{label}"),
+                value: format_number(number),
+            });
+        }
+    }
+    facts
+}
+
+/// Проверка ответа по данным программы: значения из ответа должны
+/// встречаться в данных или в вопросе пользователя
+#[derive(Debug, Default)]
+pub struct FactChecker {
+    numbers: HashSet<String>,
+    dates: HashSet<String>,
+    emails: HashSet<String>,
+    /// Меньшие числа не проверяются
+    min_number: u64,
+}
+
+impl FactChecker {
+    pub fn new(min_number: u64) -> Self {
+        FactChecker {
+            min_number,
+            ..FactChecker::default()
+        }
+    }
+
+    /// Считать значения из `text` проверенными
+    pub fn add(&mut self, text: &str) {
+        for fact in extract(text) {
+            match fact.kind {
+                FactKind::Number | FactKind::Money => {
+                    self.numbers.insert(fact.value);
+                }
+                FactKind::Date => {
+                    // "12 августа" в ответе - это день из даты "12.08.2025" в данных
+                    for part in fact.value.split('.') {
+                        self.numbers
+                            .insert(part.trim_start_matches('0').to_string());
+                    }
+                    self.dates.insert(fact.value);
+                }
+                FactKind::Email => {
+                    self.emails.insert(fact.value);
+                }
+            }
+        }
+    }
+
+    fn is_known(&self, fact: &Fact) -> bool {
+        match fact.kind {
+            FactKind::Number | FactKind::Money => {
+                let small = fact
+                    .value
+                    .parse::<f64>()
+                    .is_ok_and(|number| number < self.min_number as f64);
+                small || self.numbers.contains(&fact.value)
+            }
+            // Дата без года совпадает с той же датой любого года
+            FactKind::Date => self.dates.iter().any(|known| {
+                known == &fact.value
+                    || (known.len() != fact.value.len() && known[..5] == fact.value[..5])
+            }),
+            FactKind::Email => self.emails.contains(&fact.value),
+        }
+    }
+
+    /// Значения из ответа, которых нет ни в данных, ни в вопросе, без повторов
+    pub fn unverified(&self, answer: &str) -> Vec<Fact> {
+        let mut facts: Vec<Fact> = Vec::new();
+        for fact in extract(answer) {
+            if !self.is_known(&fact) && !facts.iter().any(|f| f.value == fact.value) {
+                facts.push(fact);
+            }
+        }
+        facts
+    }
+}
+
+/// Ответ без предложений, где встречаются `facts`. Строки, от которых
+/// ничего не осталось, удаляются.
+pub fn remove_sentences(answer: &str, facts: &[Fact]) -> String {
+    answer
+        .lines()
+        .filter_map(|line| {
+            if line.trim().is_empty() {
+                return Some(String::new());
+            }
+            let kept: Vec<&str> = citations::sentences(line)
+                .into_iter()
+                .filter(|sentence| {
+                    let values: Vec<String> =
+                        extract(sentence).into_iter().map(|f| f.value).collect();
+                    !facts.iter().any(|fact| values.contains(&fact.value))
+                })
+                .collect();
+            (!kept.is_empty()).then(|| kept.join(" "))
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(text: &str) -> Vec<(FactKind, String)> {
+        extract(text)
+            .into_iter()
+            .map(|fact| (fact.kind, fact.value))
+            .collect()
+    }
+
+    #[test]
+    fn test_extract() {
+        assert_eq!(
+            values("Стоимость 599 000 ₽ [1], бюджетных мест 51, экзамен 12.08.2025."),
+            vec![
+                (FactKind::Money, "599000".to_string()),
+                (FactKind::Number, "51".to_string()),
+                (FactKind::Date, "12.08.2025".to_string()),
+            ]
+        );
+        assert_eq!(
+            values("Около 1,5 млн руб. в год, пишите на AI@itmo.ru"),
+            vec![
+                (FactKind::Email, "ai@itmo.ru".to_string()),
+                (FactKind::Money, "1500000".to_string()),
+            ]
+        );
+        // Номера пунктов, цифры в названиях и ссылках не считаются
+        assert!(values("1. Курс GPT-4o и 3D\nhttps://abit.itmo.ru/program/2025").is_empty());
+        let facts = extract("Стоит 599 тыс. ₽ в год");
+        assert_eq!(facts[0].text, "599 тыс. ₽");
+        assert_eq!(facts[0].value, "599000");
+        // Дроби с двумя знаками после точки - не даты
+        assert_eq!(values("3.14"), vec![(FactKind::Number, "3.14".to_string())]);
+        assert_eq!(
+            values("2.50 млн"),
+            vec![(FactKind::Number, "2500000".to_string())]
+        );
+    }
+
+    #[test]
+    fn test_unverified() {
+        let mut checker = FactChecker::new(10);
+        checker.add("Стоимость обучения: 599 000 ₽");
+        checker.add("Бюджетные места: 51");
+        checker.add("Даты вступительных экзаменов: 12.08.2025");
+        checker.add("Менеджер программы: Иван, ai@itmo.ru");
+        checker.add("Есть ли 30 мест?");
+
+        let answer = "Обучение стоит 599 тыс. ₽, бюджетных мест 51, а не 30. \
+            Экзамен 12.08 и 20.08.2025, 2 попытки. Пишите ai@itmo.ru или info@itmo.ru. \
+            Целевых мест 15.";
+        let unverified: Vec<String> = checker
+            .unverified(answer)
+            .into_iter()
+            .map(|fact| fact.text)
+            .collect();
+        assert_eq!(unverified, vec!["info@itmo.ru", "20.08.2025", "15"]);
+        assert!(checker.unverified("Экзамен 12 августа").is_empty());
+    }
+
+    #[test]
+    fn test_remove_sentences() {
+        let checker = FactChecker::new(10);
+        let answer = "Обучение платное. Стоит 700 000 ₽ [1].\n\nОбщежитие есть.\nЦелевых мест 15.";
+        let facts = checker.unverified(answer);
+        assert_eq!(
+            remove_sentences(answer, &facts),
+            "Обучение платное.\n\nОбщежитие есть."
+        );
+    }
+}
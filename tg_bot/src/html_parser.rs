@@ -1,7 +1,12 @@
 use scraper::{Html, Selector};
-use serde::{Deserialize, Serialize};
-
-#[derive(Debug, Serialize, Deserialize, Clone)]
+use serde::{Deserialize, Deserializer, Serialize};
+use serde_json::Value;
+
+/// Данные программы. При чтении json типы приводятся мягко: числа и флаги
+/// могут быть записаны строками (`"14"`, `"true"`), отсутствующие поля
+/// заполняются значениями по умолчанию, а несовместимые значения - ошибка.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
 pub struct MasterProgram {
     pub title: String,
     pub description: String,
@@ -9,21 +14,30 @@ pub struct MasterProgram {
     pub study_form: String,
     pub duration: String,
     pub language: String,
+    #[serde(deserialize_with = "lenient_string")]
     pub cost: String,
+    #[serde(deserialize_with = "lenient_bool")]
     pub dormitory: bool,
+    #[serde(deserialize_with = "lenient_bool")]
     pub military_center: bool,
+    #[serde(deserialize_with = "lenient_bool")]
     pub accreditation: bool,
     pub special_programs: Vec<String>,
+    #[serde(deserialize_with = "lenient_string")]
     pub direction_code: String,
     pub direction_name: String,
-    pub budget_places: u32,
-    pub target_places: u32,
-    pub contract_places: u32,
+    #[serde(deserialize_with = "lenient_u32")]
+    pub budget_places: Option<u32>,
+    #[serde(deserialize_with = "lenient_u32")]
+    pub target_places: Option<u32>,
+    #[serde(deserialize_with = "lenient_u32")]
+    pub contract_places: Option<u32>,
     pub manager: ProgramManager,
     pub social_links: Vec<SocialLink>,
     pub exam_dates: Vec<String>,
     pub admission_methods: Vec<AdmissionMethod>,
     pub career_opportunities: String,
+    #[serde(deserialize_with = "lenient_string")]
     pub average_salary: String,
     pub team: Vec<TeamMember>,
     pub partners: Vec<String>,
@@ -32,7 +46,8 @@ pub struct MasterProgram {
     pub faq: Vec<FaqItem>,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[serde(default)]
 pub struct ProgramManager {
     pub name: String,
     pub email: String,
@@ -61,6 +76,7 @@ pub struct TeamMember {
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Scholarship {
     pub name: String,
+    #[serde(deserialize_with = "lenient_string")]
     pub amount: String,
 }
 
@@ -70,6 +86,56 @@ pub struct FaqItem {
     pub answer: String,
 }
 
+/// Число из числа или строки вида `"14"`, `null` и пустая строка - число не указано
+fn lenient_u32<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    use serde::de::Error;
+    match Value::deserialize(deserializer)? {
+        Value::Null => Ok(None),
+        Value::Number(n) => n
+            .as_u64()
+            .and_then(|n| u32::try_from(n).ok())
+            .map(Some)
+            .ok_or_else(|| D::Error::custom(format!("expected a non-negative integer, got {n}"))),
+        Value::String(s) if s.trim().is_empty() => Ok(None),
+        Value::String(s) => s
+            .trim()
+            .parse()
+            .map(Some)
+            .map_err(|_| D::Error::custom(format!("expected an integer, got {s:?}"))),
+        other => Err(D::Error::custom(format!(
+            "expected an integer, got {other}"
+        ))),
+    }
+}
+
+/// Флаг из bool, строки (`"true"`, `"да"`, `"1"`) или числа 0/1, `null` - false
+fn lenient_bool<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+    use serde::de::Error;
+    match Value::deserialize(deserializer)? {
+        Value::Null => Ok(false),
+        Value::Bool(b) => Ok(b),
+        Value::Number(n) if n.as_u64() == Some(0) => Ok(false),
+        Value::Number(n) if n.as_u64() == Some(1) => Ok(true),
+        Value::String(s) => match s.trim().to_lowercase().as_str() {
+            "true" | "да" | "есть" | "yes" | "1" => Ok(true),
+            "false" | "нет" | "no" | "0" | "" => Ok(false),
+            _ => Err(D::Error::custom(format!("expected a boolean, got {s:?}"))),
+        },
+        other => Err(D::Error::custom(format!("expected a boolean, got {other}"))),
+    }
+}
+
+/// Строка из строки или числа (`599000` - `"599000"`), `null` - пустая строка
+fn lenient_string<'de, D: Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    use serde::de::Error;
+    match Value::deserialize(deserializer)? {
+        Value::Null => Ok(String::new()),
+        Value::String(s) => Ok(s),
+        Value::Number(n) => Ok(n.to_string()),
+        other => Err(D::Error::custom(format!("expected a string, got {other}"))),
+    }
+}
+
 pub fn parse_master_program_html(
     html_content: &str,
 ) -> Result<MasterProgram, Box<dyn std::error::Error>> {
@@ -94,28 +160,26 @@ pub fn parse_master_program_html(
     let mut about_lead = String::new();
     let mut about_desc = String::new();
 
-    if !script_content.is_empty() {
-        if let Ok(json_data) = serde_json::from_str::<serde_json::Value>(&script_content) {
-            if let Some(about_obj) = json_data
-                .get("props")
-                .and_then(|p| p.get("pageProps"))
-                .and_then(|pp| pp.get("jsonProgram"))
-                .and_then(|jp| jp.get("about"))
-            {
-                about_lead = about_obj
-                    .get("lead")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or_default()
-                    .to_string();
-
-                about_desc = about_obj
-                    .get("desc")
-                    .and_then(|v| v.as_str())
-                    .unwrap_or_default()
-                    .replace("<br>", "\n")
-                    .replace("\\u003cbr\\u003e", "\n");
-            }
-        }
+    if !script_content.is_empty()
+        && let Ok(json_data) = serde_json::from_str::<serde_json::Value>(&script_content)
+        && let Some(about_obj) = json_data
+            .get("props")
+            .and_then(|p| p.get("pageProps"))
+            .and_then(|pp| pp.get("jsonProgram"))
+            .and_then(|jp| jp.get("about"))
+    {
+        about_lead = about_obj
+            .get("lead")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .to_string();
+
+        about_desc = about_obj
+            .get("desc")
+            .and_then(|v| v.as_str())
+            .unwrap_or_default()
+            .replace("<br>", "\n")
+            .replace("\\u003cbr\\u003e", "\n");
     }
 
     let description = if !about_lead.is_empty() && !about_desc.is_empty() {
@@ -139,7 +203,7 @@ pub fn parse_master_program_html(
         .map(|e| e.inner_html().trim().to_string())
         .collect();
 
-    let study_form = cards.get(0).unwrap_or(&String::new()).clone();
+    let study_form = cards.first().unwrap_or(&String::new()).clone();
     let duration = cards.get(1).unwrap_or(&String::new()).clone();
     let language = cards.get(2).unwrap_or(&String::new()).clone();
     let cost = cards.get(3).unwrap_or(&String::new()).clone();
@@ -177,9 +241,9 @@ pub fn parse_master_program_html(
         .map(|e| e.inner_html().trim().to_string())
         .collect();
 
-    let budget_places = places.get(0).and_then(|s| s.parse().ok()).unwrap_or(0);
-    let target_places = places.get(1).and_then(|s| s.parse().ok()).unwrap_or(0);
-    let contract_places = places.get(2).and_then(|s| s.parse().ok()).unwrap_or(0);
+    let budget_places = places.first().and_then(|s| s.parse().ok());
+    let target_places = places.get(1).and_then(|s| s.parse().ok());
+    let contract_places = places.get(2).and_then(|s| s.parse().ok());
 
     // Extract program manager info
     let manager_name_selector =
@@ -197,7 +261,8 @@ pub fn parse_master_program_html(
         .map(|e| e.inner_html().trim().to_string())
         .unwrap_or_default();
 
-    let manager_phone_selector = Selector::parse(".Information_manager__contact__1fPAH a[href^='tel:']")?;
+    let manager_phone_selector =
+        Selector::parse(".Information_manager__contact__1fPAH a[href^='tel:']")?;
     let manager_phone = document
         .select(&manager_phone_selector)
         .next()
@@ -252,7 +317,7 @@ pub fn parse_master_program_html(
 
     let admission_methods: Vec<AdmissionMethod> = method_titles
         .into_iter()
-        .zip(method_descriptions.into_iter())
+        .zip(method_descriptions)
         .map(|(name, description)| AdmissionMethod { name, description })
         .collect();
 
@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use serde::Deserialize;
+
+/// Настройки общего HTTP клиента для запросов к LLM
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct HttpClientConfig {
+    #[serde(
+        rename = "connect_timeout_secs",
+        deserialize_with = "crate::config::secs"
+    )]
+    pub connect_timeout: Duration,
+    /// Таймаут чтения: сколько ждать очередную порцию данных ответа.
+    /// Для стриминга это лимит паузы между чанками, а не на весь ответ.
+    #[serde(rename = "read_timeout_secs", deserialize_with = "crate::config::secs")]
+    pub read_timeout: Duration,
+    pub proxy: Option<String>,
+    pub user_agent: String,
+    #[serde(
+        rename = "http2_keep_alive_secs",
+        deserialize_with = "crate::config::secs"
+    )]
+    pub http2_keep_alive_interval: Duration,
+    #[serde(
+        rename = "pool_idle_timeout_secs",
+        deserialize_with = "crate::config::secs"
+    )]
+    pub pool_idle_timeout: Duration,
+}
+
+impl Default for HttpClientConfig {
+    fn default() -> Self {
+        HttpClientConfig {
+            connect_timeout: Duration::from_secs(5),
+            read_timeout: Duration::from_secs(30),
+            proxy: None,
+            user_agent: concat!("itmo_program_bot/", env!("CARGO_PKG_VERSION")).to_string(),
+            http2_keep_alive_interval: Duration::from_secs(30),
+            pool_idle_timeout: Duration::from_secs(90),
+        }
+    }
+}
+
+impl HttpClientConfig {
+    /// Собрать клиент. Клиент держит пул соединений и TLS сессии,
+    /// поэтому создается один раз на процесс и клонируется (клон дешевый).
+    pub fn build(&self) -> reqwest::Result<reqwest::Client> {
+        let mut builder = reqwest::Client::builder()
+            .connect_timeout(self.connect_timeout)
+            .read_timeout(self.read_timeout)
+            .user_agent(&self.user_agent)
+            .http2_keep_alive_interval(self.http2_keep_alive_interval)
+            .http2_keep_alive_while_idle(true)
+            .pool_idle_timeout(self.pool_idle_timeout)
+            .tcp_keepalive(self.http2_keep_alive_interval);
+        if let Some(proxy) = &self.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+        }
+        builder.build()
+    }
+}
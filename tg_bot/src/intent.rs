@@ -0,0 +1,617 @@
+use serde::Deserialize;
+
+use crate::llm_provider::{ChatMessage, GenerationOptions, LlmProvider, TokenUsage};
+use crate::normalize::{self, Token};
+use crate::prompts::{PromptSet, Template};
+use crate::structured_output;
+
+/// Что хочет пользователь
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Intent {
+    /// Вопрос об одной или нескольких программах либо о поступлении в целом
+    Question,
+    /// Просьба сравнить программы
+    Comparison,
+    Greeting,
+    OffTopic,
+}
+
+impl Intent {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Intent::Question => "question",
+            Intent::Comparison => "comparison",
+            Intent::Greeting => "greeting",
+            Intent::OffTopic => "off_topic",
+        }
+    }
+}
+
+/// Тема вопроса
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Topic {
+    Cost,
+    Dormitory,
+    Admission,
+    Courses,
+    Career,
+    Military,
+    Scholarships,
+    StudyForm,
+    Contacts,
+}
+
+impl Topic {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Topic::Cost => "cost",
+            Topic::Dormitory => "dormitory",
+            Topic::Admission => "admission",
+            Topic::Courses => "courses",
+            Topic::Career => "career",
+            Topic::Military => "military",
+            Topic::Scholarships => "scholarships",
+            Topic::StudyForm => "study_form",
+            Topic::Contacts => "contacts",
+        }
+    }
+}
+
+/// Ключевые слова тем. `*` в конце слова означает совпадение по началу,
+/// чтобы не зависеть от окончания: `общежити*` найдет и "общежитие", и "общежития".
+const TOPICS: &[(Topic, &[&str])] = &[
+    (
+        Topic::Cost,
+        &[
+            "стоимост*",
+            "стоит",
+            "цена",
+            "платн*",
+            "оплат*",
+            "плата",
+            "сколько денег",
+            "руб",
+            "рубль",
+            "cost",
+            "price",
+        ],
+    ),
+    (
+        Topic::Dormitory,
+        &["общежити*", "общаг*", "жиль*", "dormitory"],
+    ),
+    (
+        Topic::Admission,
+        &[
+            "поступ*",
+            "экзамен*",
+            "вступительн*",
+            "прием*",
+            "прохождени*",
+            "портфолио",
+            "олимпиад*",
+            "документ*",
+            "мест*",
+        ],
+    ),
+    (
+        Topic::Courses,
+        &[
+            "курс*",
+            "дисциплин*",
+            "предмет*",
+            "учебн* план*",
+            "изуча*",
+            "электив*",
+        ],
+    ),
+    (
+        Topic::Career,
+        &[
+            "карьер*",
+            "работ*",
+            "зарплат*",
+            "трудоустро*",
+            "ваканс*",
+            "career",
+        ],
+    ),
+    (Topic::Military, &["военн*", "армия", "армии", "отсрочк*"]),
+    (Topic::Scholarships, &["стипенди*", "грант*"]),
+    (
+        Topic::StudyForm,
+        &[
+            "очн*",
+            "заочн*",
+            "онлайн",
+            "дистанц*",
+            "длительност*",
+            "сколько лет",
+            "язык*",
+        ],
+    ),
+    (
+        Topic::Contacts,
+        &[
+            "менеджер*",
+            "контакт*",
+            "почт*",
+            "телефон*",
+            "связат*",
+            "email",
+        ],
+    ),
+];
+
+/// Слова, по которым видно, что вопрос про учебу, даже если тема не определена
+const DOMAIN_WORDS: &[&str] = &[
+    "магистр*",
+    "магистратур*",
+    "итмо",
+    "itmo",
+    "программ*",
+    "учеб*",
+    "учит*",
+    "обучени*",
+    "абитуриент*",
+    "студент*",
+    "диплом*",
+    "вуз*",
+    "университет*",
+    "семестр*",
+    "преподава*",
+    "бакалавр*",
+];
+
+const GREETINGS: &[&str] = &[
+    "привет*",
+    "здравствуй*",
+    "добрый день",
+    "добрый вечер",
+    "доброе утро",
+    "доброй ночи",
+    "hello",
+    "hi",
+    "хай",
+    "салют",
+    "start",
+];
+
+/// Слова, которые сами по себе означают просьбу сравнить
+const COMPARISON_MARKERS: &[&str] = &[
+    "сравн*",
+    "разниц*",
+    "отличи*",
+    "отлича*",
+    "чем лучше",
+    "что лучше",
+    "какая лучше",
+    "какую выбрать",
+    "vs",
+    "versus",
+];
+
+/// Названия программы и ключевые слова, по которым ее можно узнать в вопросе
+#[derive(Debug, Clone)]
+pub struct ProgramAliases {
+    pub slug: String,
+    pub name: String,
+    /// Однозначные названия, включая транслитерацию: "ai product", "ай продакт"
+    pub aliases: Vec<String>,
+    /// Слова, которые скорее указывают на программу, но не наверняка
+    pub keywords: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Classification {
+    pub intent: Intent,
+    /// Slug программ, о которых спрашивают, в порядке упоминания
+    pub programs: Vec<String>,
+    pub topic: Option<Topic>,
+    /// Уверенность от 0 до 1
+    pub confidence: f32,
+}
+
+#[derive(Debug, Clone)]
+enum PatternWord {
+    /// Слово с любым окончанием: сравнивается по основе
+    Exact(String),
+    /// `слово*`: сравнивается по началу слова
+    Prefix(String),
+}
+
+#[derive(Debug, Clone)]
+struct Pattern {
+    words: Vec<PatternWord>,
+}
+
+impl Pattern {
+    fn parse(pattern: &str) -> Self {
+        let exact = |text: &str| {
+            normalize::tokens(text)
+                .into_iter()
+                .map(|token| PatternWord::Exact(token.stem))
+                .collect::<Vec<_>>()
+        };
+        // Синонимы заменяются во всей фразе, если в ней нет `*`,
+        // иначе по словам, чтобы не потерять префиксы
+        let words = if !pattern.contains('*') {
+            exact(pattern)
+        } else {
+            pattern
+                .split_whitespace()
+                .flat_map(|part| match part.strip_suffix('*') {
+                    Some(prefix) => {
+                        let mut words = normalize::words(prefix);
+                        let last = words.pop().map(PatternWord::Prefix);
+                        words
+                            .iter()
+                            .flat_map(|word| exact(word))
+                            .chain(last)
+                            .collect()
+                    }
+                    None => exact(part),
+                })
+                .collect()
+        };
+        Pattern { words }
+    }
+
+    fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    fn matches_at(&self, tokens: &[Token], start: usize) -> bool {
+        self.words.len() <= tokens.len().saturating_sub(start)
+            && self
+                .words
+                .iter()
+                .zip(&tokens[start..])
+                .all(|(word, token)| match word {
+                    PatternWord::Exact(stem) => token.stem == *stem,
+                    PatternWord::Prefix(prefix) => token.word.starts_with(prefix.as_str()),
+                })
+    }
+}
+
+fn patterns(list: &[&str]) -> Vec<Pattern> {
+    list.iter().map(|p| Pattern::parse(p)).collect()
+}
+
+fn find_any(patterns: &[Pattern], tokens: &[Token]) -> bool {
+    (0..tokens.len()).any(|start| patterns.iter().any(|p| p.matches_at(tokens, start)))
+}
+
+struct ProgramPatterns {
+    slug: String,
+    /// Шаблон и вес совпадения
+    patterns: Vec<(Pattern, f32)>,
+}
+
+/// Уверенность, ниже которой при включенном fallback спрашиваем LLM
+pub const DEFAULT_LLM_THRESHOLD: f32 = 0.6;
+
+/// Классификатор вопросов: правила по словам с учетом границ слов,
+/// синонимов и транслитерации, а для неуверенных случаев - LLM
+pub struct IntentClassifier {
+    programs: Vec<ProgramAliases>,
+    program_patterns: Vec<ProgramPatterns>,
+    topics: Vec<(Topic, Vec<Pattern>)>,
+    domain: Vec<Pattern>,
+    greetings: Vec<Pattern>,
+    comparison: Vec<Pattern>,
+    llm_threshold: Option<f32>,
+}
+
+impl IntentClassifier {
+    pub fn new(programs: Vec<ProgramAliases>) -> Self {
+        let program_patterns = programs
+            .iter()
+            .map(|program| ProgramPatterns {
+                slug: program.slug.clone(),
+                patterns: program
+                    .aliases
+                    .iter()
+                    .map(|alias| (Pattern::parse(alias), 1.0))
+                    .chain(
+                        program
+                            .keywords
+                            .iter()
+                            .map(|keyword| (Pattern::parse(keyword), 0.6)),
+                    )
+                    .collect(),
+            })
+            .collect();
+        IntentClassifier {
+            programs,
+            program_patterns,
+            topics: TOPICS
+                .iter()
+                .map(|(topic, words)| (*topic, patterns(words)))
+                .collect(),
+            domain: patterns(DOMAIN_WORDS),
+            greetings: patterns(GREETINGS),
+            comparison: patterns(COMPARISON_MARKERS),
+            llm_threshold: None,
+        }
+    }
+
+    /// Спрашивать LLM, если уверенность правил ниже `threshold`
+    pub fn with_llm_fallback(mut self, threshold: f32) -> Self {
+        self.llm_threshold = Some(threshold);
+        self
+    }
+
+    /// Классификация только по правилам, без обращения к LLM
+    pub fn classify_rules(&self, text: &str) -> Classification {
+        let tokens = normalize::tokens(text);
+
+        // Ищем самое длинное совпадение в каждой позиции, чтобы "ai product"
+        // не засчитывался еще и как "ai"
+        let mut weights: Vec<(String, f32)> = Vec::new();
+        let mut start = 0;
+        while start < tokens.len() {
+            let best = self
+                .program_patterns
+                .iter()
+                .flat_map(|program| {
+                    program
+                        .patterns
+                        .iter()
+                        .map(move |(pattern, weight)| (&program.slug, pattern, *weight))
+                })
+                .filter(|(_, pattern, _)| pattern.matches_at(&tokens, start))
+                .max_by_key(|(_, pattern, _)| pattern.len());
+            let Some((slug, pattern, weight)) = best else {
+                start += 1;
+                continue;
+            };
+            match weights.iter_mut().find(|(found, _)| found == slug) {
+                Some((_, found_weight)) => *found_weight = found_weight.max(weight),
+                None => weights.push((slug.clone(), weight)),
+            }
+            start += pattern.len();
+        }
+        // Слабые ключевые слова не добавляют программу, если другая названа явно
+        let strongest = weights.iter().map(|(_, w)| *w).fold(0.0, f32::max);
+        weights.retain(|(_, weight)| *weight >= strongest);
+        let programs: Vec<String> = weights.into_iter().map(|(slug, _)| slug).collect();
+
+        let topic = self
+            .topics
+            .iter()
+            .find(|(_, patterns)| find_any(patterns, &tokens))
+            .map(|(topic, _)| *topic);
+
+        let greeting = self.greetings.iter().any(|p| p.matches_at(&tokens, 0));
+        if greeting && programs.is_empty() && topic.is_none() && tokens.len() <= 4 {
+            return Classification {
+                intent: Intent::Greeting,
+                programs,
+                topic,
+                confidence: 0.9,
+            };
+        }
+
+        let comparison = find_any(&self.comparison, &tokens)
+            || (programs.len() >= 2 && tokens.iter().any(|t| t.word == "или"));
+        if comparison && programs.len() != 1 {
+            let programs = if programs.is_empty() {
+                self.programs.iter().map(|p| p.slug.clone()).collect()
+            } else {
+                programs
+            };
+            return Classification {
+                intent: Intent::Comparison,
+                programs,
+                topic,
+                confidence: 0.9,
+            };
+        }
+
+        if !programs.is_empty() {
+            return Classification {
+                intent: Intent::Question,
+                programs,
+                topic,
+                confidence: strongest,
+            };
+        }
+        if topic.is_some() || find_any(&self.domain, &tokens) {
+            // Вопрос по теме, но программа не названа: возможно, уточнение
+            return Classification {
+                intent: Intent::Question,
+                programs,
+                topic,
+                confidence: 0.7,
+            };
+        }
+        Classification {
+            intent: Intent::OffTopic,
+            programs,
+            topic,
+            confidence: 0.4,
+        }
+    }
+
+    /// Классифицировать вопрос, при необходимости уточнив результат у LLM.
+    /// Возвращает также расход токенов, если LLM вызывалась.
+    pub async fn classify(
+        &self,
+        text: &str,
+        llm: &dyn LlmProvider,
+        prompts: &PromptSet,
+        options: &GenerationOptions,
+    ) -> (Classification, Option<TokenUsage>) {
+        let rules = self.classify_rules(text);
+        match self.llm_threshold {
+            Some(threshold) if rules.confidence < threshold => {}
+            _ => return (rules, None),
+        }
+        match self.classify_llm(text, llm, prompts, options).await {
+            Ok((classification, usage)) => (classification, Some(usage)),
+            Err(err) => {
+                log::warn!("LLM intent classification failed: {}", err);
+                (rules, None)
+            }
+        }
+    }
+
+    async fn classify_llm(
+        &self,
+        text: &str,
+        llm: &dyn LlmProvider,
+        prompts: &PromptSet,
+        options: &GenerationOptions,
+    ) -> anyhow::Result<(Classification, TokenUsage)> {
+        #[derive(Deserialize)]
+        struct Reply {
+            intent: Intent,
+            #[serde(default)]
+            programs: Vec<String>,
+            #[serde(default)]
+            topic: Option<Topic>,
+        }
+
+        let programs = self
+            .programs
+            .iter()
+            .map(|p| format!("{} ({})", p.slug, p.name))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let topics = TOPICS
+            .iter()
+            .map(|(topic, _)| topic.as_str())
+            .collect::<Vec<_>>()
+            .join(", ");
+        let messages = [
+            ChatMessage::system(prompts.render(
+                Template::IntentClassification,
+                &[("programs", &programs), ("topics", &topics)],
+            )),
+            ChatMessage::user(text),
+        ];
+        let response = llm.complete(&messages, options).await?;
+        let json = structured_output::extract_json(&response.text)
+            .ok_or_else(|| anyhow::anyhow!("No JSON in reply: {}", response.text))?;
+        let reply: Reply = serde_json::from_str(json)?;
+        let programs = reply
+            .programs
+            .into_iter()
+            .filter(|slug| self.programs.iter().any(|p| &p.slug == slug))
+            .collect();
+        Ok((
+            Classification {
+                intent: reply.intent,
+                programs,
+                topic: reply.topic,
+                confidence: 0.8,
+            },
+            response.usage,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn program(slug: &str, aliases: &[&str], keywords: &[&str]) -> ProgramAliases {
+        let strings = |list: &[&str]| list.iter().map(|s| s.to_string()).collect();
+        ProgramAliases {
+            slug: slug.to_string(),
+            name: slug.to_string(),
+            aliases: strings(aliases),
+            keywords: strings(keywords),
+        }
+    }
+
+    fn classify(text: &str) -> Classification {
+        IntentClassifier::new(vec![
+            program(
+                "ai",
+                &["ai", "ии", "искусственн* интеллект*"],
+                &["машинн* обучени*"],
+            ),
+            program(
+                "ai_product",
+                &["ai product", "ai продукт*"],
+                &["продукт*", "управлени* продукт*"],
+            ),
+        ])
+        .classify_rules(text)
+    }
+
+    #[test]
+    fn test_program_routing_respects_word_boundaries() {
+        assert_eq!(
+            classify("Сколько стоит AI Product?").programs,
+            ["ai_product"]
+        );
+        assert_eq!(classify("Расскажи про ai-продукт").programs, ["ai_product"]);
+        assert_eq!(classify("Что такое ИИ в ИТМО").programs, ["ai"]);
+        assert_eq!(classify("Управление продуктами").programs, ["ai_product"]);
+        // "ai" внутри слова не считается названием программы
+        assert!(classify("Где взять email менеджера").programs.is_empty());
+        // Явное название важнее ключевых слов другой программы
+        assert_eq!(
+            classify("Есть ли на AI Product машинное обучение").programs,
+            ["ai_product"]
+        );
+        // Сокращения и падежи сводятся к ключевым словам программы
+        assert_eq!(classify("Много ли ML?").programs, ["ai"]);
+        assert_eq!(classify("Про искусственный интеллект").programs, ["ai"]);
+    }
+
+    #[test]
+    fn test_intents_and_topics() {
+        let cost = classify("Сколько стоит обучение на ai?");
+        assert_eq!(cost.intent, Intent::Question);
+        assert_eq!(cost.topic, Some(Topic::Cost));
+
+        assert_eq!(classify("Привет!").intent, Intent::Greeting);
+        assert_eq!(classify("/start").intent, Intent::Greeting);
+
+        let comparison = classify("Чем отличаются программы?");
+        assert_eq!(comparison.intent, Intent::Comparison);
+        assert_eq!(comparison.programs, ["ai", "ai_product"]);
+        assert_eq!(classify("AI или AI Product?").intent, Intent::Comparison);
+
+        let follow_up = classify("А общежитие есть?");
+        assert_eq!(follow_up.intent, Intent::Question);
+        assert!(follow_up.programs.is_empty());
+
+        let off_topic = classify("Какая погода в Париже");
+        assert_eq!(off_topic.intent, Intent::OffTopic);
+        assert!(off_topic.confidence < DEFAULT_LLM_THRESHOLD);
+    }
+
+    #[test]
+    fn test_markers_do_not_match_unrelated_words() {
+        assert_eq!(classify("Сколько стоит обучение?").topic, Some(Topic::Cost));
+        assert_eq!(classify("Обучение платное?").topic, Some(Topic::Cost));
+        assert_eq!(classify("Какая цена?").topic, Some(Topic::Cost));
+        assert_eq!(classify("Цены в рублях?").topic, Some(Topic::Cost));
+        assert_eq!(classify("Сколько платить за год?").topic, Some(Topic::Cost));
+        for text in [
+            "На какой платформе проходят занятия?",
+            "Можно ли уехать на стажировку за рубеж?",
+            "Где находится центр карьеры?",
+        ] {
+            assert_ne!(classify(text).topic, Some(Topic::Cost), "{text}");
+        }
+
+        assert_eq!(
+            classify("В чем отличие ai от ai product?").intent,
+            Intent::Comparison
+        );
+        for text in [
+            "Отлично, спасибо за ответ про ai",
+            "Я отличник, возьмут на ai?",
+        ] {
+            assert_ne!(classify(text).intent, Intent::Comparison, "{text}");
+        }
+    }
+}
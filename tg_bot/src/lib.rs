@@ -0,0 +1,33 @@
+//! Бот отвечает на вопросы о магистратурах ITMO. Модули общие для бота
+//! и вспомогательных бинарников (оценка качества ответов).
+
+pub mod admin;
+pub mod answer_cache;
+pub mod billing;
+pub mod chunker;
+pub mod citations;
+pub mod config;
+pub mod curriculum;
+pub mod dialogue;
+pub mod embeddings;
+pub mod evaluation;
+pub mod fact_check;
+pub mod html_parser;
+pub mod http_client;
+pub mod intent;
+pub mod llm_error;
+pub mod llm_provider;
+pub mod mock_llm;
+pub mod normalize;
+pub mod openai_client;
+pub mod pipeline;
+pub mod programs;
+pub mod prompts;
+pub mod rate_limit;
+pub mod resilience;
+pub mod retrieval;
+pub mod storage;
+pub mod streaming;
+pub mod structured_output;
+pub mod vector_index;
+pub mod yandex_gpt_client;
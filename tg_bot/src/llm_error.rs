@@ -0,0 +1,112 @@
+use std::time::Duration;
+
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+
+/// Ошибки обращения к LLM. Тело ответа сохраняется, чтобы по логам
+/// можно было понять, что именно ответил провайдер.
+#[derive(Debug, thiserror::Error)]
+pub enum LlmError {
+    #[error("authentication failed ({status}): {body}")]
+    Auth { status: StatusCode, body: String },
+
+    #[error("rate limited, retry after {retry_after:?}: {body}")]
+    RateLimited {
+        retry_after: Option<Duration>,
+        body: String,
+    },
+
+    #[error("server error ({status}): {body}")]
+    Server { status: StatusCode, body: String },
+
+    #[error("request rejected ({status}): {body}")]
+    BadRequest { status: StatusCode, body: String },
+
+    #[error("answer blocked by content filter ({status})")]
+    ContentFiltered { status: String, body: String },
+
+    #[error("malformed response ({error}): {body}")]
+    MalformedResponse { error: String, body: String },
+
+    #[error("response has no alternatives: {body}")]
+    EmptyAlternatives { body: String },
+
+    #[error("transport error: {0}")]
+    Transport(#[from] reqwest::Error),
+
+    #[error("no answer within {0:?}")]
+    DeadlineExceeded(Duration),
+
+    #[error("provider is unhealthy, circuit breaker is open")]
+    CircuitOpen,
+
+    #[error("LLM budget exceeded")]
+    BudgetExceeded,
+}
+
+impl LlmError {
+    /// Ошибка по неуспешному HTTP ответу
+    pub fn from_status(status: StatusCode, headers: &HeaderMap, body: String) -> Self {
+        match status {
+            StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => LlmError::Auth { status, body },
+            StatusCode::TOO_MANY_REQUESTS => LlmError::RateLimited {
+                retry_after: parse_retry_after(headers),
+                body,
+            },
+            status if status.is_server_error() => LlmError::Server { status, body },
+            status => LlmError::BadRequest { status, body },
+        }
+    }
+
+    pub fn malformed(error: impl ToString, body: impl Into<String>) -> Self {
+        LlmError::MalformedResponse {
+            error: error.to_string(),
+            body: body.into(),
+        }
+    }
+
+    /// Имеет ли смысл повторять запрос
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            LlmError::RateLimited { .. }
+            | LlmError::Server { .. }
+            | LlmError::DeadlineExceeded(_) => true,
+            LlmError::Transport(err) => err.is_timeout() || err.is_connect() || err.is_request(),
+            LlmError::Auth { .. }
+            | LlmError::BadRequest { .. }
+            | LlmError::ContentFiltered { .. }
+            | LlmError::MalformedResponse { .. }
+            | LlmError::EmptyAlternatives { .. }
+            | LlmError::CircuitOpen
+            | LlmError::BudgetExceeded => false,
+        }
+    }
+
+    /// Сообщение для пользователя в Telegram, `programs` - названия программ
+    /// из `ProgramRegistry::names`
+    pub fn user_message(&self, programs: &str) -> String {
+        match self {
+            LlmError::ContentFiltered { .. } => format!(
+                "К сожалению, я не могу ответить на этот вопрос. Спросите, пожалуйста, о магистратурах {programs}."
+            ),
+            LlmError::RateLimited { .. } => {
+                "Сейчас слишком много запросов к языковой модели, попробуйте повторить вопрос через минуту.".to_string()
+            }
+            LlmError::BudgetExceeded => {
+                "Лимит запросов к языковой модели исчерпан, поэтому ответ упрощенный.".to_string()
+            }
+            _ => "Сервис ответов сейчас недоступен, поэтому ответ упрощенный.".to_string(),
+        }
+    }
+}
+
+fn parse_retry_after(headers: &HeaderMap) -> Option<Duration> {
+    headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()
+        .map(Duration::from_secs)
+}
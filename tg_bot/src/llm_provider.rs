@@ -0,0 +1,151 @@
+use std::ops::AddAssign;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::sync::watch;
+
+use crate::config::LlmConfig;
+use crate::http_client::HttpClientConfig;
+use crate::llm_error::LlmError;
+use crate::openai_client::OpenAIClient;
+use crate::yandex_gpt_client::YandexGPTClient;
+
+/// Роль автора сообщения в диалоге с моделью
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChatRole {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub text: String,
+}
+
+impl ChatMessage {
+    pub fn system(text: impl Into<String>) -> Self {
+        ChatMessage {
+            role: ChatRole::System,
+            text: text.into(),
+        }
+    }
+
+    pub fn user(text: impl Into<String>) -> Self {
+        ChatMessage {
+            role: ChatRole::User,
+            text: text.into(),
+        }
+    }
+
+    pub fn assistant(text: impl Into<String>) -> Self {
+        ChatMessage {
+            role: ChatRole::Assistant,
+            text: text.into(),
+        }
+    }
+}
+
+/// Параметры генерации, общие для всех провайдеров
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GenerationOptions {
+    pub temperature: f32,
+    pub max_tokens: u32,
+}
+
+impl Default for GenerationOptions {
+    fn default() -> Self {
+        GenerationOptions {
+            temperature: 0.0,
+            max_tokens: 4000,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TokenUsage {
+    pub input_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: TokenUsage) {
+        self.input_tokens += other.input_tokens;
+        self.completion_tokens += other.completion_tokens;
+        self.total_tokens += other.total_tokens;
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatCompletion {
+    pub text: String,
+    pub usage: TokenUsage,
+    pub model: String,
+}
+
+/// Провайдер чат-модели. Бот работает только через этот трейт,
+/// поэтому модель можно сменить конфигурацией, не трогая код пайплайна.
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    /// Короткое имя провайдера для логов
+    fn name(&self) -> &str;
+
+    /// Имя модели, по нему же ищется цена в прайс-листе
+    fn model(&self) -> &str;
+
+    async fn complete(
+        &self,
+        messages: &[ChatMessage],
+        options: &GenerationOptions,
+    ) -> Result<ChatCompletion, LlmError>;
+
+    /// Потоковая генерация: накопленный текст ответа публикуется в `partial`
+    /// по мере готовности. Провайдеры без стриминга публикуют только итоговый ответ.
+    async fn complete_stream(
+        &self,
+        messages: &[ChatMessage],
+        options: &GenerationOptions,
+        partial: &watch::Sender<String>,
+    ) -> Result<ChatCompletion, LlmError> {
+        let completion = self.complete(messages, options).await?;
+        partial.send_replace(completion.text.clone());
+        Ok(completion)
+    }
+}
+
+/// Создать провайдера по настройкам `[llm]`
+pub fn provider_from_config(
+    config: &LlmConfig,
+    http: &HttpClientConfig,
+) -> anyhow::Result<Arc<dyn LlmProvider>> {
+    let http = http.build()?;
+    let required = |value: &Option<String>, name: &str| {
+        value
+            .clone()
+            .ok_or_else(|| anyhow::anyhow!("{name} is not set"))
+    };
+    Ok(match config.provider.as_str() {
+        "yandex" => Arc::new(YandexGPTClient::new(
+            http,
+            required(&config.yandex.api_key, "llm.yandex.api_key")?,
+            config.yandex.api_url.clone(),
+            required(&config.yandex.folder_id, "llm.yandex.folder_id")?,
+            config
+                .model
+                .clone()
+                .unwrap_or_else(|| "yandexgpt".to_string()),
+            config.model_version.clone(),
+        )),
+        "openai" => Arc::new(OpenAIClient::new(
+            http,
+            required(&config.openai.api_key, "llm.openai.api_key")?,
+            config.openai.api_url.clone(),
+            required(&config.model, "llm.model")?,
+        )),
+        other => anyhow::bail!("Unknown LLM provider: {other}"),
+    })
+}
@@ -1,315 +1,270 @@
-use serde::{Deserialize, Serialize};
-use std::fs;
+use std::sync::Arc;
+use std::time::Instant;
 use teloxide::prelude::*;
+use tokio::sync::watch;
 
-pub mod html_parser;
-pub mod yandex_gpt_client;
-
-#[derive(Serialize, Deserialize, Clone)]
-struct ProgramData {
-    ai: Program,
-    ai_product: Program,
-}
-#[derive(Serialize, Deserialize, Clone)]
-struct Program {
-    url: String,
-    courses: Vec<String>,
-    info: Option<String>,
-}
+use clap::Parser;
+use tg_bot::admin;
+use tg_bot::answer_cache::AnswerCache;
+use tg_bot::billing::{BudgetGuard, PriceList};
+use tg_bot::config::{Cli, Config};
+use tg_bot::dialogue::DialogueMemory;
+use tg_bot::intent::IntentClassifier;
+use tg_bot::llm_provider::{self, LlmProvider};
+use tg_bot::mock_llm::MockYandexGpt;
+use tg_bot::pipeline::{AnswerContext, AnswerTrace, call_model, get_answer_from_llm};
+use tg_bot::programs::ProgramRegistry;
+use tg_bot::prompts::PromptStore;
+use tg_bot::rate_limit::RateLimiter;
+use tg_bot::resilience::ResilientProvider;
+use tg_bot::storage::{ExchangeRecord, LlmCallRecord, Storage};
+use tg_bot::streaming::StreamingReply;
+use tg_bot::vector_index::SemanticSearch;
 
 #[tokio::main]
 async fn main() {
     pretty_env_logger::init();
-    log::info!("Starting bot...");
     dotenv::dotenv().ok();
-    let api_key = dotenv::var("YANDEX_GPT_API_KEY").expect("YANDEX_GPT_API_KEY not set");
-    let base_url = dotenv::var("YANDEX_GPT_API_URL").expect("YANDEX_GPT_API_URL not set");
-    let folder_id = dotenv::var("YANDEX_FOLDER_ID").expect("YANDEX_FOLDER_ID not set");
-    let teloxide_token = dotenv::var("TELOXIDE_TOKEN").expect("TELOXIDE_TOKEN not set");
-    let bot = Bot::new(teloxide_token);
-    let yandex_gpt_client = yandex_gpt_client::YandexGPTClient::new(api_key, base_url, folder_id);
-    let mut data: ProgramData =
-        serde_json::from_str(&fs::read_to_string("data/programs.json").unwrap()).unwrap();
-    let ai_info = &fs::read_to_string("data/ai_parsed.json").unwrap();
-    let ai_product_info = &fs::read_to_string("data/ai_product_parsed.json").unwrap();
-
-    data.ai.info = Some(ai_info.clone());
-    data.ai_product.info = Some(ai_product_info.clone());
-    teloxide::repl(bot, move |bot: Bot, msg: Message| {
-        let data = data.clone();
-        let yandex_client = yandex_gpt_client.clone();
-        async move {
-            let text = msg.text().unwrap_or_default().to_string();
-            log::info!("Received message: {}", text);
-            let answer = get_answer_from_llm(&text, &data, &yandex_client)
-                .await
-                .unwrap_or_else(|| {
-                    "Могу отвечать только по магистратурам AI и AI Product.".to_string()
-                });
-
-            bot.send_message(msg.chat.id, answer).await?;
-            Ok(())
-        }
-    })
-    .await;
-}
-
-// Helper function to create a concise program summary
-fn create_program_summary(program: &Program, program_name: &str) -> String {
-    let info_summary = if let Some(info_str) = &program.info {
-        match serde_json::from_str::<serde_json::Value>(info_str) {
-            Ok(info_json) => {
-                format!(
-                    "Описание: {}. Стоимость: {}. Места: {} бюджетных. Форма: {}.",
-                    info_json
-                        .get("description")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("Нет описания"),
-                    info_json
-                        .get("cost")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("Не указана"),
-                    info_json
-                        .get("budget_places")
-                        .and_then(|v| v.as_u64())
-                        .map(|n| n.to_string())
-                        .unwrap_or("Не указано".to_string()),
-                    info_json
-                        .get("study_form")
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("Не указана")
-                )
-            }
-            Err(_) => "Информация недоступна".to_string(),
+    let cli = Cli::parse();
+    let mut config = match Config::load(&cli) {
+        Ok(config) => config,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(2);
         }
+    };
+    if cli.check_config {
+        println!("Configuration is valid");
+        return;
+    }
+    // Сервер должен жить до конца main
+    let _mock_llm = if cli.mock_llm {
+        let server = MockYandexGpt::start()
+            .await
+            .expect("Failed to start mock LLM server");
+        config.llm.yandex.api_url = server.url();
+        Some(server)
     } else {
-        "Информация недоступна".to_string()
+        None
     };
+    let config = Arc::new(config);
+    log::info!("Starting bot...");
+    let bot = Bot::new(config.telegram.token.clone().unwrap_or_default());
+    let data = Arc::new(
+        ProgramRegistry::load(&config.data.programs_dir).expect("Failed to load programs"),
+    );
+    log::info!(
+        "Loaded programs: {}",
+        data.iter()
+            .map(|p| p.slug.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
 
-    let courses_count = program.courses.len();
-    format!(
-        "{}: {}. Количество курсов: {}. URL: {}",
-        program_name, info_summary, courses_count, program.url
-    )
-}
-
-// Helper function to get relevant courses based on user query
-fn get_relevant_courses(program: &Program, user_text: &str, max_courses: usize) -> Vec<String> {
-    let user_lower = user_text.to_lowercase();
-    let keywords = [
-        "машинное обучение",
-        "ml",
-        "deep learning",
-        "глубокое обучение",
-        "python",
-        "данные",
-        "data",
-        "нейронные сети",
-        "neural",
-        "ai",
-        "ии",
-        "статистика",
-        "математика",
-        "алгоритм",
-        "веб",
-        "web",
-        "база данных",
-        "database",
-        "визуализация",
-        "nlp",
-        "обработка текста",
-        "изображения",
-        "gpu",
-    ];
+    let max_turns = config.dialogue.max_turns;
+    let dialogue = Arc::new(DialogueMemory::new(max_turns, config.dialogue.max_tokens));
 
-    let mut scored_courses: Vec<(String, i32)> = program
-        .courses
-        .iter()
-        .map(|course| {
-            let course_lower = course.to_lowercase();
-            let score = keywords.iter().fold(0, |acc, keyword| {
-                if user_lower.contains(keyword) && course_lower.contains(keyword) {
-                    acc + 2
-                } else if course_lower.contains(&user_lower) || user_lower.contains(&course_lower) {
-                    acc + 1
-                } else {
-                    acc
+    let storage =
+        Arc::new(Storage::open(&config.data.database_path).expect("Failed to open database"));
+    // Восстанавливаем историю диалогов после перезапуска
+    match storage.dialogue_tails(max_turns) {
+        Ok(tails) => {
+            for tail in tails {
+                for turn in tail.turns {
+                    dialogue.remember(ChatId(tail.chat_id), &turn.question, &turn.answer);
                 }
-            });
-            (course.clone(), score)
-        })
-        .collect();
+            }
+        }
+        Err(err) => log::error!("Failed to restore dialogues: {}", err),
+    }
 
-    scored_courses.sort_by(|a, b| b.1.cmp(&a.1));
-    scored_courses
-        .into_iter()
-        .take(max_courses)
-        .map(|(course, _)| course)
-        .collect()
-}
+    let prices = Arc::new(PriceList::parse(&config.llm.prices).expect("Invalid llm.prices"));
+    let budget = config.budget;
+    let admins = config.telegram.admins.clone();
+    let provider = llm_provider::provider_from_config(&config.llm, &config.http)
+        .expect("Failed to create LLM provider");
+    let llm: Arc<dyn LlmProvider> = Arc::new(BudgetGuard::new(
+        Arc::new(ResilientProvider::new(provider, config.resilience.clone())),
+        budget,
+        storage.clone(),
+    ));
+    log::info!("Using LLM provider: {} ({})", llm.name(), llm.model());
+    let semantic = Arc::new(
+        SemanticSearch::from_config(&config, &data)
+            .await
+            .map(|semantic| {
+                semantic.map_embedder(|embedder| {
+                    Arc::new(BudgetGuard::new(embedder, budget, storage.clone()))
+                })
+            }),
+    );
+    let rate_limiter = Arc::new(RateLimiter::new(config.rate_limit.clone()));
+    let cache = Arc::new(AnswerCache::new(config.cache.clone()));
+    let mut classifier = IntentClassifier::new(data.aliases());
+    if config.intent.llm_fallback {
+        classifier = classifier.with_llm_fallback(config.intent.llm_threshold);
+    }
+    let classifier = Arc::new(classifier);
+    let stream_edit_interval = config.telegram.stream_edit_interval;
+    let prompts = Arc::new(
+        PromptStore::new(&config.prompts.dir).unwrap_or_else(|errors| {
+            panic!("Failed to load prompt templates: {}", errors.join("; "))
+        }),
+    );
+    log::info!("Prompt templates version {}", prompts.current().version());
 
-/// Отправить вопрос пользователя и структуру ProgramData в LLM
-/// и получить список полей, релевантных вопросу пользователя
-async fn get_relevant_info(
-    program: &Program,
-    user_text: &str,
-    yandex_client: &yandex_gpt_client::YandexGPTClient,
-) -> anyhow::Result<String> {
-    let fields: Vec<String> = vec![
-        "title",
-        "description",
-        "institute",
-        "study_form",
-        "duration",
-        "language",
-        "cost",
-        "dormitory",
-        "military_center",
-        "accreditation",
-        "special_programs",
-        "direction_code",
-        "direction_name",
-        "budget_places",
-        "target_places",
-        "contract_places",
-        "manager",
-        "social_links",
-        "exam_dates",
-        "admission_methods",
-        "career_opportunities",
-        "average_salary",
-        "team",
-        "partners",
-        "scholarships",
-        "international_opportunities",
-        "faq",
-    ]
-    .iter()
-    .map(|s| s.to_string())
-    .collect();
+    teloxide::repl(bot, move |bot: Bot, msg: Message| {
+        let data = data.clone();
+        let llm = llm.clone();
+        let dialogue = dialogue.clone();
+        let storage = storage.clone();
+        let prices = prices.clone();
+        let admins = admins.clone();
+        let rate_limiter = rate_limiter.clone();
+        let cache = cache.clone();
+        let classifier = classifier.clone();
+        let config = config.clone();
+        let prompts = prompts.clone();
+        let semantic = semantic.clone();
+        async move {
+            let text = msg.text().unwrap_or_default().to_string();
+            log::info!("Received message: {}", text);
+            if let Err(err) = storage.touch_chat(msg.chat.id.0, msg.chat.username()) {
+                log::error!("Failed to save chat: {}", err);
+            }
+            if text.trim() == "/reset" {
+                dialogue.reset(msg.chat.id);
+                if let Err(err) = storage.reset_chat(msg.chat.id.0) {
+                    log::error!("Failed to save dialogue reset: {}", err);
+                }
+                bot.send_message(msg.chat.id, "Контекст диалога очищен.")
+                    .await?;
+                return Ok(());
+            }
+            let user_id = msg.from.as_ref().map(|user| user.id.0);
+            let admin_id = user_id.filter(|id| admins.contains(id));
+            if let Some(reply) = admin::handle_command(&text, admin_id, &storage, &budget) {
+                bot.send_message(msg.chat.id, reply).await?;
+                return Ok(());
+            }
 
-    let prompt: String = format!(
-        "Проанализируй вопрос пользователя о магистерской программе и верни список полей, которые могут быть релевантны для ответа на вопрос: '{}'. Доступные поля: {}",
-        user_text,
-        fields.join(", ")
-    );
+            // Администраторов лимиты не касаются
+            if admin_id.is_none() {
+                // У сообщений без автора (посты каналов) нет ни квоты, ни списков доступа
+                let access = user_id.and_then(|id| {
+                    storage.access_status(id).unwrap_or_else(|err| {
+                        log::error!("Failed to read access list: {}", err);
+                        None
+                    })
+                });
+                let questions_today = user_id.map_or(0, |id| {
+                    storage.questions_today(id).unwrap_or_else(|err| {
+                        log::error!("Failed to count questions: {}", err);
+                        0
+                    })
+                });
+                if let Err(rejection) =
+                    rate_limiter.check(msg.chat.id.0, &text, access, questions_today)
+                {
+                    log::warn!("Rejected message in chat {}: {:?}", msg.chat.id, rejection);
+                    if let Some(reply) = rejection.user_message() {
+                        bot.send_message(msg.chat.id, reply).await?;
+                    }
+                    return Ok(());
+                }
+            }
 
-    let response = yandex_client.get_answer("Ты LLM, который анализирует вопросы пользователей о магистерских программах и возвращает релевантные поля в виде JSON массива строк. ВАЖНО НЕ ИСПОЛЬЗУЙ форматирование markdown и ```", &prompt).await.inspect_err(|e| {
-        println!("Error getting relevant fields: {}", e)
-    })?;
-    let relevant_fields: Vec<String> = serde_json::from_str(&response)
-        .map_err(|e| anyhow::anyhow!("Failed to parse response: {}", e))?;
+            let history = dialogue.history(msg.chat.id);
+            let started = Instant::now();
+            let (partial_tx, partial_rx) = watch::channel(String::new());
+            let reply = StreamingReply::start(
+                bot.clone(),
+                msg.chat.id,
+                "Готовлю ответ…",
+                partial_rx,
+                stream_edit_interval,
+            )
+            .await?;
+            let mut trace = AnswerTrace::default();
+            let prompt_set = prompts.current();
+            let context = AnswerContext {
+                data: &data,
+                llm: llm.as_ref(),
+                cache: &cache,
+                classifier: &classifier,
+                config: &config,
+                prompts: &prompt_set,
+                semantic: semantic.as_ref().as_ref(),
+            };
+            let answer =
+                get_answer_from_llm(&text, &history, &context, &partial_tx, &mut trace).await;
+            drop(partial_tx);
+            if trace.error.is_none() {
+                dialogue.remember(msg.chat.id, &text, &answer);
+            }
 
-    let program_info = program.info.clone().unwrap_or_default();
-    let relevant_info =
-        if let Ok(info_json) = serde_json::from_str::<serde_json::Value>(&program_info) {
-            relevant_fields
+            let calls: Vec<LlmCallRecord> = trace
+                .calls
                 .iter()
-                .filter_map(|field| {
-                    info_json
-                        .get(field)
-                        .map(|value| format!("{}: {}", field, value))
+                .map(|(purpose, usage)| {
+                    let model = call_model(purpose, llm.as_ref(), &config);
+                    LlmCallRecord {
+                        purpose: purpose.to_string(),
+                        model: model.to_string(),
+                        usage: *usage,
+                        cost: prices.cost(model, usage),
+                        cached: false,
+                    }
                 })
-                .collect::<Vec<_>>()
-                .join(", ")
-        } else {
-            "Информация недоступна".to_string()
-        };
-
-    Ok(relevant_info)
-}
-
-async fn get_answer_from_llm(
-    user_text: &str,
-    data: &ProgramData,
-    yandex_client: &yandex_gpt_client::YandexGPTClient,
-) -> Option<String> {
-    // Determine which program the user is asking about
-    let user_lower = user_text.to_lowercase();
-    let asking_about_ai_product = [
-        "ai product",
-        "ai-продукт",
-        "ai product",
-        "продукт",
-        "Управление",
-    ]
-    .iter()
-    .any(|&s| user_lower.contains(s));
-    let asking_about_ai = [
-        "ai",
-        "искусственный интеллект",
-        "машинное обучение",
-        "глубокое обучение",
-        "нейронные сети",
-    ]
-    .iter()
-    .any(|&s| user_lower.contains(s));
-
-    // Helper to build system prompt for a program
-    async fn build_program_prompt(
-        program: &Program,
-        program_name: &str,
-        user_text: &str,
-        yandex_client: &yandex_gpt_client::YandexGPTClient,
-    ) -> String {
-        let summary = create_program_summary(program, program_name);
-        let relevant_info = get_relevant_info(program, user_text, yandex_client)
-            .await
-            .unwrap_or_default();
-        let relevant_courses = get_relevant_courses(program, user_text, 10).join(", ");
-        format!(
-            "Ты консультант по магистратуре {program_name} в ITMO. Программа:\n{summary}\n{relevant_courses}\nИнформация релевантная вопросу:\n{relevant_info}\nОтвечай кратко и по существу. Если вопрос не по теме, скажи что не можешь ответить."
-        )
-    }
-
-    let system_prompt = if asking_about_ai_product == asking_about_ai {
-        // User asking about both programs - provide summaries
-        let ai_summary = create_program_summary(&data.ai, "AI");
-        let ai_product_summary = create_program_summary(&data.ai_product, "AI Product");
-        let ai_relevant_info = get_relevant_info(&data.ai, user_text, &yandex_client)
-            .await
-            .unwrap_or_default();
-        format!(
-            "Ты консультант по магистратурам ITMO. У нас есть 2 программы:\n{ai_summary}\n{ai_product_summary}\nИнформация релевантная вопросу:\n{ai_relevant_info}\nОтвечай кратко и по существу. Если вопрос не по теме, скажи что не можешь ответить."
-        )
-    } else if asking_about_ai_product {
-        build_program_prompt(&data.ai_product, "AI Product", user_text, &yandex_client).await
-    } else if asking_about_ai {
-        build_program_prompt(&data.ai, "AI", user_text, &yandex_client).await
-    } else {
-        // General query - provide brief info about both
-        "Ты консультант по магистратурам ITMO. У нас есть 2 AI программы: 'Искусственный интеллект' и 'AI Product'. Отвечай кратко. Если вопрос не по теме, скажи что не можешь ответить.".to_string()
-    };
+                .chain(
+                    trace
+                        .cache_hits
+                        .iter()
+                        .map(|(purpose, usage)| LlmCallRecord {
+                            purpose: purpose.to_string(),
+                            model: llm.model().to_string(),
+                            usage: *usage,
+                            cost: 0.0,
+                            cached: true,
+                        }),
+                )
+                .collect();
+            let cost: f64 = calls.iter().map(|call| call.cost).sum();
+            log::info!(
+                "Answered chat {}: {} tokens (input {}, completion {}), cost {:.2} ₽, {} cache hits",
+                msg.chat.id,
+                trace.usage.total_tokens,
+                trace.usage.input_tokens,
+                trace.usage.completion_tokens,
+                cost,
+                trace.cache_hits.len()
+            );
 
-    // Используем Yandex GPT для получения ответа
-    match yandex_client.get_answer(&system_prompt, user_text).await {
-        Ok(response) => Some(response),
-        Err(err) => {
-            log::error!("Error getting answer from Yandex GPT: {}", err);
-            // Fallback to simple logic if API fails
-            if asking_about_ai_product {
-                let courses = get_relevant_courses(&data.ai_product, user_text, 3);
-                Some(format!(
-                    "AI Product программа. Релевантные курсы: {}",
-                    if courses.is_empty() {
-                        "программирование, ML, продуктовая разработка".to_string()
-                    } else {
-                        courses.join(", ")
-                    }
-                ))
-            } else if asking_about_ai {
-                let courses = get_relevant_courses(&data.ai, user_text, 3);
-                Some(format!(
-                    "AI программа. Релевантные курсы: {}",
-                    if courses.is_empty() {
-                        "машинное обучение, глубокое обучение, Python".to_string()
-                    } else {
-                        courses.join(", ")
-                    }
-                ))
-            } else {
-                None
+            let record = ExchangeRecord {
+                chat_id: msg.chat.id.0,
+                user_id,
+                question: text.clone(),
+                answer: Some(answer.clone()),
+                program: trace.program,
+                relevant_fields: trace.relevant_fields,
+                usage: trace.usage,
+                cost,
+                calls,
+                latency_ms: started.elapsed().as_millis() as u64,
+                parse_failures: trace.parse_failures,
+                intent: trace.intent.map(str::to_string),
+                topic: trace.topic.map(str::to_string),
+                prompt_version: trace.prompt_version,
+                unverified_facts: trace.unverified_facts,
+                error: trace.error,
+            };
+            if let Err(err) = storage.record_exchange(&record) {
+                log::error!("Failed to save message: {}", err);
             }
+
+            reply.finish(&answer).await?;
+            Ok(())
         }
-    }
+    })
+    .await;
 }
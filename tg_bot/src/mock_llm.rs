@@ -0,0 +1,384 @@
+use std::collections::VecDeque;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use axum::Router;
+use axum::body::{Body, Bytes};
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::Response;
+use axum::routing::post;
+use futures_util::StreamExt;
+use serde_json::json;
+use tokio::task::JoinHandle;
+
+use crate::embeddings::EmbeddingRequest;
+use crate::normalize;
+use crate::yandex_gpt_client::{Role, YandexGPTRequest};
+
+pub const COMPLETION_PATH: &str = "/foundationModels/v1/completion";
+pub const EMBEDDING_PATH: &str = "/foundationModels/v1/textEmbedding";
+
+/// Размерность векторов мока
+const EMBEDDING_DIMENSIONS: usize = 64;
+
+/// Ответ мок-сервера на один запрос
+#[derive(Debug, Clone)]
+pub struct MockReply {
+    status: StatusCode,
+    text: String,
+    /// Части ответа в режиме stream, `None` - весь текст одним куском
+    chunks: Option<Vec<String>>,
+    /// Тело ответа как есть, вместо ответа в формате API
+    raw_body: Option<String>,
+    headers: Vec<(String, String)>,
+    alternative_status: String,
+    usage: Option<(u64, u64)>,
+    latency: Duration,
+    chunk_delay: Duration,
+}
+
+impl MockReply {
+    pub fn text(text: impl Into<String>) -> Self {
+        MockReply {
+            status: StatusCode::OK,
+            text: text.into(),
+            chunks: None,
+            raw_body: None,
+            headers: Vec::new(),
+            alternative_status: "ALTERNATIVE_STATUS_FINAL".to_string(),
+            usage: None,
+            latency: Duration::ZERO,
+            chunk_delay: Duration::ZERO,
+        }
+    }
+
+    /// Ответ, который в режиме stream отдается частями
+    pub fn chunks(chunks: &[&str]) -> Self {
+        MockReply {
+            chunks: Some(chunks.iter().map(|c| c.to_string()).collect()),
+            ..MockReply::text(chunks.concat())
+        }
+    }
+
+    /// Ошибка в формате Yandex Cloud
+    pub fn error(status: u16, message: &str) -> Self {
+        let status = StatusCode::from_u16(status).expect("invalid HTTP status");
+        let body = json!({
+            "error": {
+                "httpCode": status.as_u16(),
+                "httpStatus": status.canonical_reason().unwrap_or_default(),
+                "message": message,
+            }
+        });
+        MockReply {
+            status,
+            raw_body: Some(body.to_string()),
+            ..MockReply::text("")
+        }
+    }
+
+    /// Успешный ответ с телом, которое не разбирается как ответ API
+    pub fn malformed(body: impl Into<String>) -> Self {
+        MockReply {
+            raw_body: Some(body.into()),
+            ..MockReply::text("")
+        }
+    }
+
+    /// Ответ, заблокированный фильтром контента
+    pub fn content_filter() -> Self {
+        MockReply {
+            alternative_status: "ALTERNATIVE_STATUS_CONTENT_FILTER".to_string(),
+            ..MockReply::text("")
+        }
+    }
+
+    pub fn with_header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_string(), value.to_string()));
+        self
+    }
+
+    /// Счетчики токенов, по умолчанию считаются по словам запроса и ответа
+    pub fn with_usage(mut self, input_tokens: u64, output_tokens: u64) -> Self {
+        self.usage = Some((input_tokens, output_tokens));
+        self
+    }
+
+    /// Задержка перед ответом
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = latency;
+        self
+    }
+
+    /// Задержка между частями ответа в режиме stream
+    pub fn with_chunk_delay(mut self, delay: Duration) -> Self {
+        self.chunk_delay = delay;
+        self
+    }
+}
+
+/// Запрос, который получил мок-сервер
+#[derive(Debug, Clone)]
+pub struct MockRequest {
+    pub authorization: Option<String>,
+    pub body: YandexGPTRequest,
+}
+
+impl MockRequest {
+    /// Текст последнего сообщения пользователя
+    pub fn last_user_text(&self) -> &str {
+        self.body
+            .messages
+            .iter()
+            .rev()
+            .find(|m| matches!(m.role, Role::User))
+            .map_or("", |m| m.text.as_str())
+    }
+}
+
+type Responder = Box<dyn Fn(&MockRequest) -> MockReply + Send + Sync>;
+
+struct MockState {
+    script: Mutex<VecDeque<MockReply>>,
+    requests: Mutex<Vec<MockRequest>>,
+    embeddings: Mutex<Vec<EmbeddingRequest>>,
+    responder: Responder,
+}
+
+/// Локальный сервер с API completion YandexGPT для тестов и режима `--mock-llm`.
+/// Отвечает по очереди ответами из сценария, когда сценарий закончился -
+/// через `responder`. Векторы текстов отдает по `EMBEDDING_PATH`, см. `mock_embedding`.
+/// Останавливается при drop.
+pub struct MockYandexGpt {
+    addr: SocketAddr,
+    state: Arc<MockState>,
+    server: JoinHandle<()>,
+}
+
+impl MockYandexGpt {
+    /// Сервер, который без сценария отвечает `default_reply`
+    pub async fn start() -> std::io::Result<Self> {
+        Self::start_with(default_reply).await
+    }
+
+    pub async fn start_with(
+        responder: impl Fn(&MockRequest) -> MockReply + Send + Sync + 'static,
+    ) -> std::io::Result<Self> {
+        let state = Arc::new(MockState {
+            script: Mutex::new(VecDeque::new()),
+            requests: Mutex::new(Vec::new()),
+            embeddings: Mutex::new(Vec::new()),
+            responder: Box::new(responder),
+        });
+        let app = Router::new()
+            .route(COMPLETION_PATH, post(completion))
+            .route(EMBEDDING_PATH, post(embedding))
+            .with_state(state.clone());
+        let listener = tokio::net::TcpListener::bind(("127.0.0.1", 0)).await?;
+        let addr = listener.local_addr()?;
+        let server = tokio::spawn(async move {
+            if let Err(err) = axum::serve(listener, app).await {
+                log::error!("Mock LLM server failed: {}", err);
+            }
+        });
+        log::info!("Mock LLM server listening on {}", addr);
+        Ok(MockYandexGpt {
+            addr,
+            state,
+            server,
+        })
+    }
+
+    /// Адрес для `llm.yandex.api_url`
+    pub fn url(&self) -> String {
+        format!("http://{}{}", self.addr, COMPLETION_PATH)
+    }
+
+    /// Адрес для `embeddings.api_url`
+    pub fn embedding_url(&self) -> String {
+        format!("http://{}{}", self.addr, EMBEDDING_PATH)
+    }
+
+    /// Добавить ответ в конец сценария
+    pub fn push(&self, reply: MockReply) {
+        self.state.script.lock().unwrap().push_back(reply);
+    }
+
+    /// Все полученные запросы по порядку
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+
+    /// Все запросы векторов по порядку
+    pub fn embedding_requests(&self) -> Vec<EmbeddingRequest> {
+        self.state.embeddings.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockYandexGpt {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+/// Ответ без сценария: на просьбы вернуть JSON - пустой массив,
+/// на остальное - повтор вопроса, чтобы по ответу было видно, что он от мока
+pub fn default_reply(request: &MockRequest) -> MockReply {
+    let wants_json = request
+        .body
+        .messages
+        .iter()
+        .any(|m| matches!(m.role, Role::System) && m.text.contains("JSON"));
+    if wants_json {
+        MockReply::text("[]")
+    } else {
+        MockReply::text(format!(
+            "Тестовый ответ на вопрос: {}",
+            request.last_user_text()
+        ))
+    }
+}
+
+/// Вектор мока - мешок основ слов, разложенный по измерениям хэшем:
+/// близки тексты с общими словами, синонимы из словаря совпадают
+pub fn mock_embedding(text: &str) -> Vec<f32> {
+    let mut vector = vec![0.0; EMBEDDING_DIMENSIONS];
+    for token in normalize::tokens(text) {
+        let hash = token
+            .stem
+            .bytes()
+            .fold(0xcbf29ce484222325_u64, |hash, byte| {
+                (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+            });
+        vector[(hash % EMBEDDING_DIMENSIONS as u64) as usize] += 1.0;
+    }
+    vector
+}
+
+fn word_count(text: &str) -> u64 {
+    text.split_whitespace().count() as u64
+}
+
+fn completion_json(text: &str, status: &str, usage: (u64, u64)) -> String {
+    let (input, output) = usage;
+    json!({
+        "result": {
+            "alternatives": [{
+                "message": {"role": "assistant", "text": text},
+                "status": status,
+            }],
+            // Как и в настоящем API, счетчики строками
+            "usage": {
+                "inputTextTokens": input.to_string(),
+                "completionTokens": output.to_string(),
+                "totalTokens": (input + output).to_string(),
+            },
+            "modelVersion": "mock",
+        }
+    })
+    .to_string()
+}
+
+async fn completion(
+    State(state): State<Arc<MockState>>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Response {
+    let body: YandexGPTRequest = match serde_json::from_slice(&body) {
+        Ok(body) => body,
+        Err(err) => {
+            let reply = MockReply::error(400, &format!("invalid request: {err}"));
+            return response(reply.status, &[], Body::from(reply.raw_body.unwrap()));
+        }
+    };
+    let request = MockRequest {
+        authorization: headers
+            .get("authorization")
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string),
+        body,
+    };
+    let scripted = state.script.lock().unwrap().pop_front();
+    let reply = scripted.unwrap_or_else(|| (state.responder)(&request));
+    let stream = request.body.completion_options.stream;
+    let input_tokens = request
+        .body
+        .messages
+        .iter()
+        .map(|m| word_count(&m.text))
+        .sum();
+    state.requests.lock().unwrap().push(request);
+
+    tokio::time::sleep(reply.latency).await;
+    let usage = reply
+        .usage
+        .unwrap_or((input_tokens, word_count(&reply.text)));
+    if let Some(raw) = reply.raw_body {
+        return response(reply.status, &reply.headers, Body::from(raw));
+    }
+    if !stream {
+        let body = completion_json(&reply.text, &reply.alternative_status, usage);
+        return response(reply.status, &reply.headers, Body::from(body));
+    }
+
+    // В режиме stream каждая строка - накопленный текст, счетчики растут
+    // вместе с ним, последняя строка со статусом из ответа
+    let chunks = reply.chunks.unwrap_or_else(|| vec![reply.text.clone()]);
+    let count = chunks.len();
+    let mut text = String::new();
+    let mut lines = Vec::with_capacity(count);
+    for (i, chunk) in chunks.iter().enumerate() {
+        text.push_str(chunk);
+        let status = if i + 1 == count {
+            reply.alternative_status.as_str()
+        } else {
+            "ALTERNATIVE_STATUS_PARTIAL"
+        };
+        let output = if i + 1 == count {
+            usage.1
+        } else {
+            word_count(&text)
+        };
+        lines.push(completion_json(&text, status, (usage.0, output)) + "\n");
+    }
+    let delay = reply.chunk_delay;
+    let lines = futures_util::stream::iter(lines.into_iter().enumerate()).then(
+        move |(i, line)| async move {
+            if i > 0 {
+                tokio::time::sleep(delay).await;
+            }
+            Ok::<_, Infallible>(Bytes::from(line))
+        },
+    );
+    response(reply.status, &reply.headers, Body::from_stream(lines))
+}
+
+async fn embedding(State(state): State<Arc<MockState>>, body: Bytes) -> Response {
+    let request: EmbeddingRequest = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(err) => {
+            let reply = MockReply::error(400, &format!("invalid request: {err}"));
+            return response(reply.status, &[], Body::from(reply.raw_body.unwrap()));
+        }
+    };
+    let body = json!({
+        "embedding": mock_embedding(&request.text),
+        "numTokens": word_count(&request.text).to_string(),
+        "modelVersion": "mock",
+    });
+    state.embeddings.lock().unwrap().push(request);
+    response(StatusCode::OK, &[], Body::from(body.to_string()))
+}
+
+fn response(status: StatusCode, headers: &[(String, String)], body: Body) -> Response {
+    let mut builder = Response::builder()
+        .status(status)
+        .header("content-type", "application/json");
+    for (name, value) in headers {
+        builder = builder.header(name, value);
+    }
+    builder.body(body).expect("invalid mock response header")
+}
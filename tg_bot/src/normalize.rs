@@ -0,0 +1,143 @@
+use std::sync::LazyLock;
+
+use rust_stemmers::{Algorithm, Stemmer};
+
+/// Синонимы и сокращения, первое написание в группе - каноническое.
+/// Фразы сравниваются по основам слов, так что падеж не важен.
+const SYNONYMS: &[&[&str]] = &[
+    &["машинное обучение", "мо", "ml", "machine learning"],
+    &[
+        "ai",
+        "ии",
+        "искусственный интеллект",
+        "artificial intelligence",
+    ],
+    &[
+        "nlp",
+        "обработка естественного языка",
+        "natural language processing",
+    ],
+    &["глубокое обучение", "deep learning"],
+    &["компьютерное зрение", "computer vision"],
+    &["llm", "большие языковые модели", "большая языковая модель"],
+    &["базы данных", "бд", "database", "databases"],
+];
+
+static RUSSIAN: LazyLock<Stemmer> = LazyLock::new(|| Stemmer::create(Algorithm::Russian));
+static ENGLISH: LazyLock<Stemmer> = LazyLock::new(|| Stemmer::create(Algorithm::English));
+
+/// Фразы словаря в виде основ и каноническая форма каждой из них
+static PHRASES: LazyLock<Vec<(Vec<String>, Vec<Token>)>> = LazyLock::new(|| {
+    let mut phrases: Vec<(Vec<String>, Vec<Token>)> = SYNONYMS
+        .iter()
+        .flat_map(|group| {
+            let canonical: Vec<Token> = words(group[0]).into_iter().map(Token::new).collect();
+            group.iter().map(move |phrase| {
+                let stems = words(phrase).iter().map(|word| stem(word)).collect();
+                (stems, canonical.clone())
+            })
+        })
+        .collect();
+    // Сначала длинные фразы, чтобы "обработка естественного языка" не разбилась на части
+    phrases.sort_by_key(|(stems, _)| std::cmp::Reverse(stems.len()));
+    phrases
+});
+
+/// Слово текста и его основа
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Token {
+    pub word: String,
+    pub stem: String,
+}
+
+impl Token {
+    fn new(word: String) -> Self {
+        Token {
+            stem: stem(&word),
+            word,
+        }
+    }
+}
+
+/// Разбить текст на слова: нижний регистр, `ё` как `е`, без пунктуации.
+/// Дефис тоже разделяет слова, так что "ai-product" и "ai product" совпадают.
+pub fn words(text: &str) -> Vec<String> {
+    text.to_lowercase()
+        .replace('ё', "е")
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+/// Основа слова: для кириллицы русский стеммер, для латиницы английский,
+/// числа, смешанные и короткие слова без изменений. Короткие - это обычно
+/// сокращения, у "ии" стеммер отрезал бы окончание и получил союз "и".
+pub fn stem(word: &str) -> String {
+    if word.chars().count() <= 3 {
+        word.to_string()
+    } else if word.chars().all(|c| matches!(c, 'а'..='я' | 'ё')) {
+        RUSSIAN.stem(word).into_owned()
+    } else if word.chars().all(|c| c.is_ascii_lowercase()) {
+        ENGLISH.stem(word).into_owned()
+    } else {
+        word.to_string()
+    }
+}
+
+/// Слова текста с основами, синонимы и сокращения из словаря заменены
+/// на каноническую форму: "МО", "ML" и "машинного обучения" дают одно и то же
+pub fn tokens(text: &str) -> Vec<Token> {
+    let tokens: Vec<Token> = words(text).into_iter().map(Token::new).collect();
+    let mut result = Vec::with_capacity(tokens.len());
+    let mut start = 0;
+    while start < tokens.len() {
+        let rest = &tokens[start..];
+        let synonym = PHRASES.iter().find(|(stems, _)| {
+            stems.len() <= rest.len() && stems.iter().zip(rest).all(|(stem, t)| *stem == t.stem)
+        });
+        match synonym {
+            Some((stems, canonical)) => {
+                result.extend(canonical.iter().cloned());
+                start += stems.len();
+            }
+            None => {
+                result.push(rest[0].clone());
+                start += 1;
+            }
+        }
+    }
+    result
+}
+
+/// Основы слов текста через пробел: одинаковые по смыслу формулировки
+/// вопроса дают одну строку
+pub fn normalize(text: &str) -> String {
+    tokens(text)
+        .into_iter()
+        .map(|token| token.stem)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inflections_and_synonyms_match() {
+        assert_eq!(normalize("обучения"), normalize("обучением"));
+        assert_eq!(normalize("Учёба"), normalize("учеба"));
+        assert_eq!(normalize("МО"), normalize("машинного обучения"));
+        assert_eq!(normalize("ML"), normalize("машинное обучение"));
+        assert_eq!(normalize("ИИ"), "ai");
+        assert_eq!(
+            normalize("курсы обработки естественного языка"),
+            normalize("курс NLP")
+        );
+        // Сокращение заменяется только целым словом
+        assert_eq!(normalize("html"), "html");
+        assert_eq!(normalize("и ИИ"), "и ai");
+        assert_eq!(normalize("AI-Product"), "ai product");
+    }
+}
@@ -0,0 +1,143 @@
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::llm_error::LlmError;
+use crate::llm_provider::{
+    ChatCompletion, ChatMessage, ChatRole, GenerationOptions, LlmProvider, TokenUsage,
+};
+
+// Клиент для любого OpenAI-совместимого `/chat/completions` API
+// (OpenAI, vLLM, Ollama, OpenRouter и т.п.)
+#[derive(Debug, Clone)]
+pub struct OpenAIClient {
+    http: reqwest::Client,
+    api_key: String,
+    base_url: String,
+    model: String,
+}
+
+impl OpenAIClient {
+    pub fn new(http: reqwest::Client, api_key: String, base_url: String, model: String) -> Self {
+        OpenAIClient {
+            http,
+            api_key,
+            base_url,
+            model,
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAIClient {
+    fn name(&self) -> &str {
+        "openai"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(
+        &self,
+        messages: &[ChatMessage],
+        options: &GenerationOptions,
+    ) -> Result<ChatCompletion, LlmError> {
+        let request_body = ChatCompletionRequest {
+            model: self.model.clone(),
+            messages: messages.iter().map(Message::from).collect(),
+            temperature: options.temperature,
+            max_tokens: options.max_tokens,
+        };
+
+        let response = self
+            .http
+            .post(&self.base_url)
+            .bearer_auth(&self.api_key)
+            .json(&request_body)
+            .send()
+            .await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(LlmError::from_status(status, &headers, body));
+        }
+
+        let response: ChatCompletionResponse =
+            serde_json::from_str(&body).map_err(|err| LlmError::malformed(err, &body))?;
+        let choice = response
+            .choices
+            .into_iter()
+            .next()
+            .ok_or_else(|| LlmError::EmptyAlternatives { body: body.clone() })?;
+        if choice.finish_reason.as_deref() == Some("content_filter") {
+            return Err(LlmError::ContentFiltered {
+                status: "content_filter".to_string(),
+                body,
+            });
+        }
+        Ok(ChatCompletion {
+            text: choice.message.content,
+            usage: response.usage.map(TokenUsage::from).unwrap_or_default(),
+            model: response.model,
+        })
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChatCompletionRequest {
+    pub model: String,
+    pub messages: Vec<Message>,
+    pub temperature: f32,
+    pub max_tokens: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Message {
+    pub role: String,
+    pub content: String,
+}
+
+impl From<&ChatMessage> for Message {
+    fn from(message: &ChatMessage) -> Self {
+        let role = match message.role {
+            ChatRole::System => "system",
+            ChatRole::User => "user",
+            ChatRole::Assistant => "assistant",
+        };
+        Message {
+            role: role.to_string(),
+            content: message.text.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletionResponse {
+    pub model: String,
+    pub choices: Vec<Choice>,
+    pub usage: Option<Usage>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Choice {
+    pub message: Message,
+    pub finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct Usage {
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub total_tokens: u64,
+}
+
+impl From<Usage> for TokenUsage {
+    fn from(usage: Usage) -> Self {
+        TokenUsage {
+            input_tokens: usage.prompt_tokens,
+            completion_tokens: usage.completion_tokens,
+            total_tokens: usage.total_tokens,
+        }
+    }
+}
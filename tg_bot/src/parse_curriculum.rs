@@ -0,0 +1,72 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use clap::Parser;
+
+use tg_bot::curriculum::Curriculum;
+
+/// Разобрать учебные планы программ из pdf: `data/<name>.pdf` -> `data/<name>_curriculum.json`.
+/// Чтобы бот использовал план вместо `courses`, укажите json в `curriculum_path` манифеста.
+#[derive(Debug, Parser)]
+struct Args {
+    /// Файлы pdf, по умолчанию все pdf в `--data-dir`
+    pdfs: Vec<PathBuf>,
+    #[arg(long, default_value = "data")]
+    data_dir: PathBuf,
+}
+
+fn main() -> anyhow::Result<()> {
+    pretty_env_logger::init();
+    let args = Args::parse();
+    let pdfs = if args.pdfs.is_empty() {
+        find_pdfs(&args.data_dir)?
+    } else {
+        args.pdfs
+    };
+    if pdfs.is_empty() {
+        anyhow::bail!(
+            "No pdf files in {}, download the study plans from the program pages first",
+            args.data_dir.display()
+        );
+    }
+    for pdf in pdfs {
+        let stem = pdf.file_stem().unwrap_or_default().to_string_lossy();
+        let output = pdf.with_file_name(format!("{stem}_curriculum.json"));
+        parse_curriculum(&pdf, &output)?;
+    }
+    Ok(())
+}
+
+fn find_pdfs(dir: &Path) -> anyhow::Result<Vec<PathBuf>> {
+    let mut pdfs: Vec<PathBuf> = fs::read_dir(dir)
+        .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", dir.display(), e))?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()?;
+    pdfs.retain(|path| path.extension().is_some_and(|ext| ext == "pdf"));
+    pdfs.sort();
+    Ok(pdfs)
+}
+
+fn parse_curriculum(pdf: &Path, output_json: &Path) -> anyhow::Result<()> {
+    let curriculum = Curriculum::from_pdf(pdf)?;
+    let json_output = serde_json::to_string_pretty(&curriculum)?;
+
+    // Как и у parse_html: векторный индекс строится и по курсам
+    let changed = fs::read_to_string(output_json).map_or(true, |old| old != json_output);
+    fs::write(output_json, &json_output)?;
+
+    let credits: u32 = curriculum.disciplines.iter().map(|d| d.credits).sum();
+    println!(
+        "Parsed {} disciplines ({} credits) from {} to {}",
+        curriculum.disciplines.len(),
+        credits,
+        pdf.display(),
+        output_json.display()
+    );
+    if changed {
+        println!(
+            "Curriculum changed, the vector index will be rebuilt on bot start or by `cargo run --bin build_index`"
+        );
+    }
+    Ok(())
+}
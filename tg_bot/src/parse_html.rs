@@ -18,10 +18,19 @@ fn parse_html(html_path: &str, output_json: &str) -> Result<(), Box<dyn std::err
     // Convert to JSON
     let json_output = serde_json::to_string_pretty(&program)?;
 
+    // Векторный индекс для семантического поиска строится по этим данным,
+    // бот перестроит его при старте, если данные изменились
+    let changed = fs::read_to_string(output_json).map_or(true, |old| old != json_output);
+
     // Save to file
     fs::write(output_json, &json_output)?;
 
     println!("\nParsed data saved to: {}", output_json);
+    if changed {
+        println!(
+            "Program data changed, the vector index will be rebuilt on bot start or by `cargo run --bin build_index`"
+        );
+    }
 
     Ok(())
 }
@@ -0,0 +1,617 @@
+use tokio::sync::watch;
+
+use crate::answer_cache::{AnswerCache, Cached};
+use crate::chunker::Chunk;
+use crate::citations::{self, Citations};
+use crate::config::Config;
+use crate::fact_check::{self, Fact, FactChecker};
+use crate::intent::{Intent, IntentClassifier};
+use crate::llm_error::LlmError;
+use crate::llm_provider::{ChatMessage, ChatRole, LlmProvider, TokenUsage};
+use crate::programs::{Program, ProgramRegistry};
+use crate::prompts::{PromptSet, Template};
+use crate::retrieval::SearchIndex;
+use crate::structured_output::{self, OutputError};
+use crate::vector_index::SemanticSearch;
+
+/// Что происходило при ответе на вопрос: сохраняется для аналитики
+#[derive(Debug, Default)]
+pub struct AnswerTrace {
+    pub program: Option<String>,
+    pub relevant_fields: Vec<String>,
+    pub usage: TokenUsage,
+    /// Вызовы LLM и векторы вопросов (`embedding`): назначение и расход токенов
+    pub calls: Vec<(&'static str, TokenUsage)>,
+    /// Вызовы, замененные кэшем: назначение и сколько токенов стоил исходный вызов
+    pub cache_hits: Vec<(&'static str, TokenUsage)>,
+    /// Сколько раз не удалось разобрать структурированный ответ модели
+    pub parse_failures: u32,
+    pub intent: Option<&'static str>,
+    pub topic: Option<&'static str>,
+    /// Версия шаблонов промптов, с которыми строился ответ
+    pub prompt_version: Option<String>,
+    /// Числа, даты и адреса из ответа модели, которых нет в данных программы
+    pub unverified_facts: Vec<String>,
+    pub error: Option<String>,
+}
+
+impl AnswerTrace {
+    pub fn record_call(&mut self, purpose: &'static str, usage: TokenUsage) {
+        self.usage += usage;
+        self.calls.push((purpose, usage));
+    }
+
+    pub fn record_cache_hit(&mut self, purpose: &'static str, usage: TokenUsage) {
+        self.cache_hits.push((purpose, usage));
+    }
+}
+
+/// Модель, которой сделан вызов `purpose` из `AnswerTrace::calls`, для цены:
+/// вектор вопроса считает модель эмбеддингов, остальное - LLM
+pub fn call_model<'a>(purpose: &str, llm: &'a dyn LlmProvider, config: &'a Config) -> &'a str {
+    match purpose {
+        "embedding" => &config.embeddings.query_model,
+        _ => llm.model(),
+    }
+}
+
+// Helper function to create a concise program summary
+fn create_program_summary(program: &Program) -> String {
+    let or_missing = |value: &str, missing: &'static str| {
+        if value.trim().is_empty() {
+            missing.to_string()
+        } else {
+            value.to_string()
+        }
+    };
+    let info_summary = match &program.info {
+        Some(info) => format!(
+            "Описание: {}. Стоимость: {}. Бюджетных мест: {}. Форма: {}.",
+            or_missing(&info.description, "Нет описания"),
+            or_missing(&info.cost, "Не указана"),
+            info.budget_places
+                .map_or_else(|| "Не указано".to_string(), |n| n.to_string()),
+            or_missing(&info.study_form, "Не указана")
+        ),
+        None => "Информация недоступна".to_string(),
+    };
+
+    let courses_count = program.courses.len();
+    format!(
+        "{}: {}. Количество курсов: {}. URL: {}",
+        program.short_name, info_summary, courses_count, program.url
+    )
+}
+
+/// Курсы программы, в названиях которых есть слова из вопроса, по убыванию оценки
+fn get_relevant_courses(
+    index: &SearchIndex,
+    program: &Program,
+    user_text: &str,
+    max_courses: usize,
+) -> Vec<String> {
+    index
+        .search(user_text, max_courses, |chunk| {
+            chunk.program == program.slug && chunk.field == "courses"
+        })
+        .into_iter()
+        .filter_map(|hit| program.courses.get(hit.chunk.index?).cloned())
+        .collect()
+}
+
+/// Записать поля фрагментов в `trace` без повторов
+fn record_fields(trace: &mut AnswerTrace, chunks: &[&Chunk]) {
+    for chunk in chunks {
+        if !trace.relevant_fields.contains(&chunk.field) {
+            trace.relevant_fields.push(chunk.field.clone());
+        }
+    }
+}
+
+/// Фрагменты программы, нужные для ответа на вопрос: найденные поиском, если вопрос
+/// почти целиком нашелся в данных, иначе фрагменты полей, которые выбрала LLM
+async fn get_relevant_chunks<'a>(
+    program: &Program,
+    user_text: &str,
+    context: &AnswerContext<'a>,
+    trace: &mut AnswerTrace,
+) -> anyhow::Result<Vec<&'a Chunk>> {
+    let AnswerContext {
+        data,
+        cache,
+        config,
+        ..
+    } = *context;
+    // Если вопрос почти целиком нашелся в данных программы, поля у LLM не спрашиваем
+    let hits = data
+        .index()
+        .search(user_text, config.retrieval.max_passages, |chunk| {
+            chunk.program == program.slug
+        });
+    if hits
+        .first()
+        .is_some_and(|hit| hit.coverage >= config.retrieval.min_coverage)
+    {
+        log::info!(
+            "Using {} retrieved chunks for {} instead of field selection",
+            hits.len(),
+            program.slug
+        );
+        let chunks: Vec<&Chunk> = hits.iter().map(|hit| hit.chunk).collect();
+        record_fields(trace, &chunks);
+        return Ok(chunks);
+    }
+
+    let relevant_fields = match cache.fields(user_text, Some(&program.slug)) {
+        Some(cached) => {
+            trace.record_cache_hit("field_selection", cached.usage);
+            cached.value
+        }
+        None => {
+            let selected = select_fields(user_text, context, trace).await?;
+            cache.put_fields(user_text, Some(&program.slug), selected.clone());
+            selected.value
+        }
+    };
+    trace
+        .relevant_fields
+        .extend(relevant_fields.iter().cloned());
+
+    Ok(data
+        .index()
+        .chunks()
+        .iter()
+        .filter(|chunk| chunk.program == program.slug && relevant_fields.contains(&chunk.field))
+        .collect())
+}
+
+/// Фрагменты программы, близкие к вопросу по смыслу, которых еще нет среди `found`.
+/// Находят ответ, даже когда вопрос сформулирован совсем другими словами, чем данные.
+async fn get_similar_chunks<'a>(
+    semantic: &'a SemanticSearch,
+    program: &Program,
+    user_text: &str,
+    found: &[&Chunk],
+    config: &Config,
+    trace: &mut AnswerTrace,
+) -> Vec<&'a Chunk> {
+    let embeddings = &config.embeddings;
+    let search = semantic
+        .search(user_text, embeddings.top_k, embeddings.min_score, |chunk| {
+            chunk.program == program.slug && !found.iter().any(|f| f.id == chunk.id)
+        })
+        .await;
+    match search {
+        Ok((hits, usage)) => {
+            trace.record_call("embedding", usage);
+            hits.into_iter().map(|hit| hit.chunk).collect()
+        }
+        Err(err) => {
+            log::warn!("Semantic search failed: {}", err);
+            Vec::new()
+        }
+    }
+}
+
+/// Данные программы для промпта, фрагменты пронумерованы для ссылок в ответе
+fn render_relevant_info<'a>(
+    program: &Program,
+    chunks: &[&'a Chunk],
+    citations: &mut Citations<'a>,
+) -> String {
+    if program.info.is_none() && chunks.is_empty() {
+        "Информация недоступна".to_string()
+    } else {
+        citations.render(chunks)
+    }
+}
+
+/// Спросить у LLM, какие из полей `data.fields` нужны для ответа на вопрос
+async fn select_fields(
+    user_text: &str,
+    context: &AnswerContext<'_>,
+    trace: &mut AnswerTrace,
+) -> anyhow::Result<Cached<Vec<String>>> {
+    let AnswerContext {
+        llm,
+        config,
+        prompts,
+        ..
+    } = *context;
+    let fields: Vec<&str> = config.data.fields.iter().map(String::as_str).collect();
+    let field_list = fields.join(", ");
+
+    let mut messages = vec![
+        ChatMessage::system(prompts.render(Template::FieldSelectionSystem, &[])),
+        ChatMessage::user(prompts.render(
+            Template::FieldSelection,
+            &[("question", user_text), ("fields", &field_list)],
+        )),
+    ];
+    let mut usage = TokenUsage::default();
+    // Если ответ не разобрался, переспрашиваем один раз, показав модели ошибку
+    let mut attempt = 1;
+    loop {
+        let response = llm
+            .complete(&messages, &config.generation)
+            .await
+            .inspect_err(|e| log::error!("Error getting relevant fields: {}", e))?;
+        trace.record_call("field_selection", response.usage);
+        usage += response.usage;
+
+        let err = match structured_output::parse_field_list(&response.text, &fields) {
+            Ok(fields) => {
+                return Ok(Cached {
+                    value: fields,
+                    usage,
+                });
+            }
+            Err(err) => err,
+        };
+        trace.parse_failures += 1;
+        log::warn!(
+            "Failed to parse field selection (attempt {}): {}. Reply: {}",
+            attempt,
+            err,
+            response.text
+        );
+        if attempt == 1 {
+            messages.push(ChatMessage::assistant(response.text));
+            messages.push(ChatMessage::user(prompts.render(
+                Template::FieldSelectionRetry,
+                &[("error", &err.to_string()), ("fields", &field_list)],
+            )));
+            attempt += 1;
+            continue;
+        }
+        return match err {
+            // Лучше часть полей, чем ни одного
+            OutputError::UnknownFields { known, .. } if !known.is_empty() => Ok(Cached {
+                value: known,
+                usage,
+            }),
+            err => Err(anyhow::anyhow!("Failed to parse field selection: {}", err)),
+        };
+    }
+}
+
+/// Все, что нужно для ответа на вопрос
+pub struct AnswerContext<'a> {
+    pub data: &'a ProgramRegistry,
+    pub llm: &'a dyn LlmProvider,
+    pub cache: &'a AnswerCache,
+    pub classifier: &'a IntentClassifier,
+    pub config: &'a Config,
+    pub prompts: &'a PromptSet,
+    /// Семантический поиск, `None`, если векторы не настроены
+    pub semantic: Option<&'a SemanticSearch>,
+}
+
+fn list_facts(facts: &[Fact]) -> String {
+    facts
+        .iter()
+        .map(|fact| fact.text.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Сверить числа, суммы, даты и адреса из ответа с данными программ и вопросом.
+/// С ответом, где есть выдуманные значения, поступаем по `verification.action`.
+async fn verify_answer(
+    answer: String,
+    programs: &[&Program],
+    messages: &[ChatMessage],
+    context: &AnswerContext<'_>,
+    trace: &mut AnswerTrace,
+) -> String {
+    let AnswerContext {
+        data,
+        llm,
+        config,
+        prompts,
+        ..
+    } = *context;
+    let verification = &config.verification;
+    // Без программы сверять не с чем
+    if verification.action == "off" || programs.is_empty() {
+        return answer;
+    }
+    let mut checker = FactChecker::new(verification.min_number);
+    for message in messages.iter().filter(|m| m.role == ChatRole::User) {
+        checker.add(&message.text);
+    }
+    for program in programs {
+        checker.add(&create_program_summary(program));
+        for chunk in data.index().chunks() {
+            if chunk.program == program.slug {
+                checker.add(&chunk.render());
+            }
+        }
+    }
+    let facts = checker.unverified(&answer);
+    if facts.is_empty() {
+        return answer;
+    }
+    let question = messages.last().map_or("", |message| message.text.as_str());
+    log::warn!(
+        "Unverified facts in answer to {:?}: {}",
+        question,
+        list_facts(&facts)
+    );
+    trace.unverified_facts = facts.iter().map(|fact| fact.text.clone()).collect();
+    let links: Vec<&str> = programs
+        .iter()
+        .map(|program| program.url.as_str())
+        .collect();
+    let note = |facts: &[Fact]| {
+        prompts.render(
+            Template::FactCheckNote,
+            &[("facts", &list_facts(facts)), ("links", &links.join(", "))],
+        )
+    };
+    match verification.action.as_str() {
+        "append" => format!("{answer}\n\n{}", note(&facts)),
+        "rewrite" => {
+            let rewritten = fact_check::remove_sentences(&answer, &facts);
+            if rewritten.is_empty() {
+                note(&facts)
+            } else {
+                rewritten
+            }
+        }
+        "regenerate" => {
+            let mut retry = messages.to_vec();
+            retry.push(ChatMessage::assistant(answer.clone()));
+            retry.push(ChatMessage::user(prompts.render(
+                Template::FactCheckRetry,
+                &[("facts", &list_facts(&facts))],
+            )));
+            match llm.complete(&retry, &config.generation).await {
+                Ok(completion) => {
+                    trace.record_call("fact_check", completion.usage);
+                    let remaining = checker.unverified(&completion.text);
+                    if remaining.is_empty() {
+                        return completion.text;
+                    }
+                    log::warn!(
+                        "Regenerated answer to {:?} still has unverified facts: {}",
+                        question,
+                        list_facts(&remaining)
+                    );
+                    format!("{}\n\n{}", completion.text, note(&remaining))
+                }
+                Err(err) => {
+                    log::warn!("Failed to regenerate answer with unverified facts: {}", err);
+                    format!("{answer}\n\n{}", note(&facts))
+                }
+            }
+        }
+        _ => answer,
+    }
+}
+
+fn greeting_reply(data: &ProgramRegistry, prompts: &PromptSet) -> String {
+    prompts.render(Template::Greeting, &[("programs", &data.names())])
+}
+
+fn off_topic_reply(data: &ProgramRegistry, prompts: &PromptSet) -> String {
+    prompts.render(Template::OffTopic, &[("programs", &data.names())])
+}
+
+/// Ответить на вопрос пользователя: классифицировать вопрос, собрать
+/// промпт по данным программ и получить ответ от LLM. Частичный ответ
+/// публикуется в `partial`, подробности ответа записываются в `trace`.
+pub async fn get_answer_from_llm(
+    user_text: &str,
+    history: &[ChatMessage],
+    context: &AnswerContext<'_>,
+    partial: &watch::Sender<String>,
+    trace: &mut AnswerTrace,
+) -> String {
+    let AnswerContext {
+        data,
+        llm,
+        cache,
+        classifier,
+        config,
+        prompts,
+        ..
+    } = *context;
+    trace.prompt_version = Some(prompts.version().to_string());
+    cache.set_prompt_version(prompts.version());
+
+    let (mut classification, usage) = classifier
+        .classify(user_text, llm, prompts, &config.generation)
+        .await;
+    if let Some(usage) = usage {
+        trace.record_call("intent", usage);
+    }
+    if classification.intent == Intent::Question && classification.programs.is_empty() {
+        // Уточняющий вопрос: берем программу из последнего вопроса, где она упоминалась
+        if let Some(previous) = history
+            .iter()
+            .rev()
+            .filter(|message| message.role == ChatRole::User)
+            .map(|message| classifier.classify_rules(&message.text).programs)
+            .find(|programs| !programs.is_empty())
+        {
+            classification.programs = previous;
+        }
+    }
+    log::info!("Classified question: {:?}", classification);
+    trace.intent = Some(classification.intent.as_str());
+    trace.topic = classification.topic.map(|topic| topic.as_str());
+    match classification.intent {
+        Intent::Greeting => return greeting_reply(data, prompts),
+        // Правила не отказывают сами, отказываем, только если уверены (например, по ответу LLM)
+        Intent::OffTopic if classification.confidence >= 0.7 => {
+            return off_topic_reply(data, prompts);
+        }
+        _ => {}
+    }
+    let programs: Vec<&Program> = classification
+        .programs
+        .iter()
+        .filter_map(|slug| data.get(slug))
+        .collect();
+
+    // Helper to build system prompt for a program
+    async fn build_program_prompt<'a>(
+        program: &Program,
+        user_text: &str,
+        context: &AnswerContext<'a>,
+        citations: &mut Citations<'a>,
+        trace: &mut AnswerTrace,
+    ) -> String {
+        let AnswerContext {
+            data,
+            config,
+            prompts,
+            semantic,
+            ..
+        } = *context;
+        let program_name = &program.short_name;
+        let summary = create_program_summary(program);
+        let mut chunks = get_relevant_chunks(program, user_text, context, trace)
+            .await
+            .unwrap_or_default();
+        if let Some(semantic) = semantic {
+            let similar =
+                get_similar_chunks(semantic, program, user_text, &chunks, config, trace).await;
+            record_fields(trace, &similar);
+            chunks.extend(similar);
+        }
+        let relevant_info = render_relevant_info(program, &chunks, citations);
+        let max_courses = config.courses.max_in_prompt;
+        let mut courses = get_relevant_courses(data.index(), program, user_text, max_courses);
+        // Остальные места занимаем курсами по порядку, чтобы модель видела программу
+        for course in &program.courses {
+            if courses.len() >= max_courses {
+                break;
+            }
+            if !courses.contains(course) {
+                courses.push(course.clone());
+            }
+        }
+        let relevant_courses = courses.join(", ");
+        prompts.render(
+            Template::Program,
+            &[
+                ("program_name", program_name),
+                ("summary", &summary),
+                ("courses", &relevant_courses),
+                ("relevant_info", &relevant_info),
+            ],
+        )
+    }
+
+    trace.program = match programs.as_slice() {
+        [program] => Some(program.slug.clone()),
+        _ => None,
+    };
+
+    // Ответ на вопрос без контекста диалога не зависит от истории, его можно кэшировать
+    let cacheable = history.is_empty();
+    if cacheable && let Some(cached) = cache.answer(user_text, trace.program.as_deref()) {
+        trace.record_cache_hit("answer", cached.usage);
+        return cached.value;
+    }
+
+    let mut citations = Citations::new();
+    let system_prompt = match programs.as_slice() {
+        // General query - provide brief info about all programs
+        [] => prompts.render(Template::General, &[("programs", &data.names())]),
+        [program] => build_program_prompt(program, user_text, context, &mut citations, trace).await,
+        programs => {
+            // User asking about several programs - provide summaries
+            let mut summaries = Vec::new();
+            let mut relevant_info = Vec::new();
+            for program in programs {
+                summaries.push(create_program_summary(program));
+                let chunks = get_relevant_chunks(program, user_text, context, trace)
+                    .await
+                    .unwrap_or_default();
+                relevant_info.push(format!(
+                    "{}:\n{}",
+                    program.short_name,
+                    render_relevant_info(program, &chunks, &mut citations)
+                ));
+            }
+            prompts.render(
+                Template::Programs,
+                &[
+                    ("summaries", &summaries.join("\n")),
+                    ("relevant_info", &relevant_info.join("\n")),
+                ],
+            )
+        }
+    };
+
+    // Используем LLM для получения ответа, передавая предыдущие реплики диалога
+    let mut messages = vec![ChatMessage::system(system_prompt)];
+    messages.extend_from_slice(history);
+    messages.push(ChatMessage::user(user_text));
+    let result = llm
+        .complete_stream(&messages, &config.generation, partial)
+        .await;
+    let err = match result {
+        Ok(completion) => {
+            trace.record_call("answer", completion.usage);
+            let mut answer =
+                verify_answer(completion.text, &programs, &messages, context, trace).await;
+            if !citations.is_empty() {
+                for claim in citations::uncited_numbers(&answer) {
+                    log::warn!("Uncited numeric claim in answer: {}", claim);
+                }
+            }
+            if let Some(footer) = citations.footer(&answer, data) {
+                answer = format!("{answer}\n\n{footer}");
+            }
+            if cacheable {
+                cache.put_answer(
+                    user_text,
+                    trace.program.as_deref(),
+                    Cached {
+                        value: answer.clone(),
+                        usage: completion.usage,
+                    },
+                );
+            }
+            return answer;
+        }
+        Err(err) => err,
+    };
+    log::error!("Error getting answer from {}: {}", llm.name(), err);
+    trace.error = Some(err.to_string());
+    if let LlmError::ContentFiltered { .. } = err {
+        return err.user_message(&data.names());
+    }
+
+    // Fallback to simple logic if API fails
+    let fallback = if programs.is_empty() {
+        off_topic_reply(data, prompts)
+    } else {
+        programs
+            .iter()
+            .map(|program| {
+                let courses = get_relevant_courses(
+                    data.index(),
+                    program,
+                    user_text,
+                    config.courses.max_in_fallback,
+                );
+                format!(
+                    "{} программа. Релевантные курсы: {}",
+                    program.short_name,
+                    if courses.is_empty() {
+                        program.highlights.join(", ")
+                    } else {
+                        courses.join(", ")
+                    }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+    format!("{}\n\n{}", err.user_message(&data.names()), fallback)
+}
@@ -0,0 +1,291 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::chunker;
+use crate::curriculum::{Curriculum, Discipline};
+use crate::html_parser::MasterProgram;
+use crate::intent::ProgramAliases;
+use crate::retrieval::SearchIndex;
+
+/// Поля `MasterProgram`, из которых LLM выбирает релевантные вопросу
+pub const PROGRAM_FIELDS: &[&str] = &[
+    "title",
+    "description",
+    "institute",
+    "study_form",
+    "duration",
+    "language",
+    "cost",
+    "dormitory",
+    "military_center",
+    "accreditation",
+    "special_programs",
+    "direction_code",
+    "direction_name",
+    "budget_places",
+    "target_places",
+    "contract_places",
+    "manager",
+    "social_links",
+    "exam_dates",
+    "admission_methods",
+    "career_opportunities",
+    "average_salary",
+    "team",
+    "partners",
+    "scholarships",
+    "international_opportunities",
+    "faq",
+];
+
+/// Описание программы в `data/programs/<slug>.json`
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProgramManifest {
+    pub slug: String,
+    /// Полное название: "Искусственный интеллект"
+    pub name: String,
+    /// Короткое название для промптов и ответов: "AI"
+    pub short_name: String,
+    /// Однозначные названия программы, см. `intent::ProgramAliases`
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Темы, которые упоминаются в упрощенном ответе без LLM
+    #[serde(default)]
+    pub highlights: Vec<String>,
+    pub url: String,
+    #[serde(default)]
+    pub courses: Vec<String>,
+    /// Путь к json от парсера относительно каталога манифестов
+    pub info_path: PathBuf,
+    /// Путь к учебному плану от `parse_curriculum` относительно каталога манифестов,
+    /// дисциплины из него заменяют `courses`
+    #[serde(default)]
+    pub curriculum_path: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Program {
+    pub slug: String,
+    pub name: String,
+    pub short_name: String,
+    pub aliases: Vec<String>,
+    pub keywords: Vec<String>,
+    pub highlights: Vec<String>,
+    pub url: String,
+    /// Названия дисциплин: из учебного плана, если он есть, иначе из манифеста
+    pub courses: Vec<String>,
+    /// Дисциплины учебного плана в том же порядке, что `courses`, пусто без плана
+    pub curriculum: Vec<Discipline>,
+    /// Данные с сайта программы, `None`, если парсер для нее еще не запускали
+    pub info: Option<MasterProgram>,
+}
+
+/// Все программы, о которых знает бот. Чтобы добавить программу,
+/// достаточно положить ее манифест в каталог и json от парсера рядом.
+#[derive(Debug, Clone)]
+pub struct ProgramRegistry {
+    programs: Vec<Program>,
+    index: SearchIndex,
+}
+
+impl ProgramRegistry {
+    /// Загрузить манифесты `*.json` из каталога, программы сортируются по slug
+    pub fn load(dir: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let dir = dir.as_ref();
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", dir.display(), e))?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        paths.retain(|path| path.extension().is_some_and(|ext| ext == "json"));
+        paths.sort();
+
+        let mut programs: Vec<Program> = Vec::new();
+        for path in paths {
+            let manifest: ProgramManifest = serde_json::from_str(&fs::read_to_string(&path)?)
+                .map_err(|e| anyhow::anyhow!("Invalid manifest {}: {}", path.display(), e))?;
+            if programs.iter().any(|p| p.slug == manifest.slug) {
+                anyhow::bail!(
+                    "Duplicate program slug {} in {}",
+                    manifest.slug,
+                    path.display()
+                );
+            }
+            let info_path = dir.join(&manifest.info_path);
+            let info = match fs::read_to_string(&info_path) {
+                Ok(info) => Some(parse_info(&info).map_err(|e| {
+                    anyhow::anyhow!("Invalid program info {}: {}", info_path.display(), e)
+                })?),
+                Err(err) => {
+                    log::warn!(
+                        "No parsed info for {} at {}: {}",
+                        manifest.slug,
+                        info_path.display(),
+                        err
+                    );
+                    None
+                }
+            };
+            let curriculum = match &manifest.curriculum_path {
+                Some(path) => Curriculum::load(dir.join(path))
+                    .inspect_err(|e| log::warn!("No curriculum for {}: {}", manifest.slug, e))
+                    .unwrap_or_default(),
+                None => Curriculum::default(),
+            };
+            let courses = if curriculum.disciplines.is_empty() {
+                manifest.courses
+            } else {
+                curriculum.course_names()
+            };
+            programs.push(Program {
+                slug: manifest.slug,
+                name: manifest.name,
+                short_name: manifest.short_name,
+                aliases: manifest.aliases,
+                keywords: manifest.keywords,
+                highlights: manifest.highlights,
+                url: manifest.url,
+                courses,
+                curriculum: curriculum.disciplines,
+                info,
+            });
+        }
+        if programs.is_empty() {
+            anyhow::bail!("No program manifests in {}", dir.display());
+        }
+        let index = SearchIndex::new(programs.iter().flat_map(chunker::program_chunks).collect());
+        log::info!("Indexed {} chunks", index.len());
+        Ok(ProgramRegistry { programs, index })
+    }
+
+    pub fn get(&self, slug: &str) -> Option<&Program> {
+        self.programs.iter().find(|p| p.slug == slug)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Program> {
+        self.programs.iter()
+    }
+
+    /// Поиск по курсам, FAQ, способам поступления, описаниям и команде всех программ
+    pub fn index(&self) -> &SearchIndex {
+        &self.index
+    }
+
+    /// Названия программ через запятую: "'Искусственный интеллект', 'AI Product'"
+    pub fn names(&self) -> String {
+        self.programs
+            .iter()
+            .map(|p| format!("'{}'", p.name))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Названия и ключевые слова для классификатора вопросов
+    pub fn aliases(&self) -> Vec<ProgramAliases> {
+        self.programs
+            .iter()
+            .map(|p| ProgramAliases {
+                slug: p.slug.clone(),
+                name: p.name.clone(),
+                aliases: p.aliases.clone(),
+                keywords: p.keywords.clone(),
+            })
+            .collect()
+    }
+}
+
+/// Прочитать json от парсера с мягким приведением типов и проверкой схемы
+pub fn parse_info(json: &str) -> anyhow::Result<MasterProgram> {
+    let info: MasterProgram = serde_json::from_str(json)?;
+    if info.title.trim().is_empty() {
+        anyhow::bail!("program title is empty");
+    }
+    Ok(info)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_program_fields_match_master_program() {
+        let value = serde_json::to_value(MasterProgram::default()).unwrap();
+        let mut fields: Vec<&str> = value
+            .as_object()
+            .unwrap()
+            .keys()
+            .map(|k| k.as_str())
+            .collect();
+        let mut expected = PROGRAM_FIELDS.to_vec();
+        fields.sort();
+        expected.sort();
+        assert_eq!(fields, expected);
+    }
+
+    #[test]
+    fn test_parse_info_coerces_types() {
+        let info = parse_info(
+            r#"{"title": "AI Product", "budget_places": "14", "dormitory": "да",
+                "cost": 599000, "average_salary": null, "target_places": null,
+                "contract_places": ""}"#,
+        )
+        .unwrap();
+        assert_eq!(info.budget_places, Some(14));
+        // Незаполненное число не превращается в 0 мест
+        assert_eq!(info.target_places, None);
+        assert_eq!(info.contract_places, None);
+        assert!(info.dormitory);
+        assert_eq!(info.cost, "599000");
+        assert!(info.faq.is_empty());
+
+        assert!(parse_info(r#"{"title": "AI", "budget_places": "много"}"#).is_err());
+        assert!(parse_info(r#"{"title": "AI", "faq": "нет"}"#).is_err());
+        assert!(parse_info(r#"{"description": "без названия"}"#).is_err());
+    }
+
+    #[test]
+    fn test_load_bundled_manifests() {
+        let registry = ProgramRegistry::load("data/programs").unwrap();
+        let slugs: Vec<_> = registry.iter().map(|p| p.slug.as_str()).collect();
+        assert_eq!(slugs, ["ai", "ai_product"]);
+
+        let ai = registry.get("ai").unwrap();
+        assert!(!ai.courses.is_empty());
+        assert_eq!(ai.info.as_ref().unwrap().budget_places, Some(51));
+        assert!(registry.get("unknown").is_none());
+    }
+
+    #[test]
+    fn test_curriculum_replaces_manifest_courses() {
+        // Как и данные парсера, план лежит рядом с каталогом манифестов
+        let dir = std::env::temp_dir().join(format!("programs_{}", fastrand::u64(..)));
+        fs::create_dir_all(dir.join("programs")).unwrap();
+        fs::write(
+            dir.join("programs/ai.json"),
+            r#"{"slug": "ai", "name": "Искусственный интеллект", "short_name": "AI",
+                "url": "https://abit.itmo.ru/program/master/ai", "info_path": "missing.json",
+                "courses": ["Старый курс"], "curriculum_path": "../ai_plan.json"}"#,
+        )
+        .unwrap();
+        let curriculum = Curriculum::parse("1 Машинное обучение 6 216 Экзамен");
+        fs::write(
+            dir.join("ai_plan.json"),
+            serde_json::to_string(&curriculum).unwrap(),
+        )
+        .unwrap();
+
+        let registry = ProgramRegistry::load(dir.join("programs")).unwrap();
+        let ai = registry.get("ai").unwrap();
+        assert_eq!(ai.courses, vec!["Машинное обучение"]);
+        assert_eq!(ai.curriculum, curriculum.disciplines);
+        let course = &registry.index().chunks()[0];
+        assert_eq!(
+            course.text,
+            "Машинное обучение: 1 семестр, 6 з.е., 216 ч., экзамен, обязательная дисциплина"
+        );
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// Шаблоны промптов и ответов, которые лежат в `<prompts.dir>/<name>.txt`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Template {
+    FieldSelectionSystem,
+    FieldSelection,
+    FieldSelectionRetry,
+    Program,
+    Programs,
+    General,
+    Greeting,
+    OffTopic,
+    FactCheckRetry,
+    FactCheckNote,
+    IntentClassification,
+}
+
+impl Template {
+    pub const ALL: [Template; 11] = [
+        Template::FieldSelectionSystem,
+        Template::FieldSelection,
+        Template::FieldSelectionRetry,
+        Template::Program,
+        Template::Programs,
+        Template::General,
+        Template::Greeting,
+        Template::OffTopic,
+        Template::FactCheckRetry,
+        Template::FactCheckNote,
+        Template::IntentClassification,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Template::FieldSelectionSystem => "field_selection_system",
+            Template::FieldSelection => "field_selection",
+            Template::FieldSelectionRetry => "field_selection_retry",
+            Template::Program => "program",
+            Template::Programs => "programs",
+            Template::General => "general",
+            Template::Greeting => "greeting",
+            Template::OffTopic => "off_topic",
+            Template::FactCheckRetry => "fact_check_retry",
+            Template::FactCheckNote => "fact_check_note",
+            Template::IntentClassification => "intent_classification",
+        }
+    }
+
+    /// Плейсхолдеры `{name}`, которые можно использовать в шаблоне
+    pub fn placeholders(self) -> &'static [&'static str] {
+        match self {
+            Template::FieldSelectionSystem => &[],
+            Template::FieldSelection => &["question", "fields"],
+            Template::FieldSelectionRetry => &["error", "fields"],
+            Template::Program => &["program_name", "summary", "courses", "relevant_info"],
+            Template::Programs => &["summaries", "relevant_info"],
+            Template::General | Template::Greeting | Template::OffTopic => &["programs"],
+            Template::FactCheckRetry => &["facts"],
+            Template::FactCheckNote => &["facts", "links"],
+            Template::IntentClassification => &["programs", "topics"],
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Placeholder(String),
+}
+
+/// Разбить шаблон на текст и плейсхолдеры. Плейсхолдер - имя из строчных
+/// латинских букв и `_` в фигурных скобках, остальные скобки остаются текстом,
+/// поэтому JSON в примерах экранировать не нужно.
+fn parse_template(text: &str) -> Vec<Segment> {
+    let mut segments = Vec::new();
+    let mut rest = text;
+    let mut literal = String::new();
+    while let Some(start) = rest.find('{') {
+        literal.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let name_len = after
+            .find(|c: char| !(c.is_ascii_lowercase() || c == '_'))
+            .unwrap_or(after.len());
+        if name_len > 0 && after[name_len..].starts_with('}') {
+            if !literal.is_empty() {
+                segments.push(Segment::Text(std::mem::take(&mut literal)));
+            }
+            segments.push(Segment::Placeholder(after[..name_len].to_string()));
+            rest = &after[name_len + 1..];
+        } else {
+            literal.push('{');
+            rest = after;
+        }
+    }
+    literal.push_str(rest);
+    if !literal.is_empty() {
+        segments.push(Segment::Text(literal));
+    }
+    segments
+}
+
+/// Набор шаблонов одной версии
+#[derive(Debug, Clone)]
+pub struct PromptSet {
+    version: String,
+    templates: HashMap<Template, Vec<Segment>>,
+}
+
+impl PromptSet {
+    /// Загрузить все шаблоны из каталога. Ошибки (нет файла, неизвестный
+    /// плейсхолдер) собираются списком, чтобы исправить их за один раз.
+    pub fn load(dir: impl AsRef<Path>) -> Result<Self, Vec<String>> {
+        let dir = dir.as_ref();
+        let mut errors = Vec::new();
+        let mut templates = HashMap::new();
+        // FNV-1a: версия должна совпадать между сборками и запусками
+        let mut hash: u32 = 0x811c_9dc5;
+        for template in Template::ALL {
+            let path = dir.join(format!("{}.txt", template.name()));
+            let text = match std::fs::read_to_string(&path) {
+                Ok(text) => text,
+                Err(err) => {
+                    errors.push(format!("{}: {}", path.display(), err));
+                    continue;
+                }
+            };
+            let text = text.trim_end();
+            for byte in text.bytes().chain([0]) {
+                hash = (hash ^ u32::from(byte)).wrapping_mul(0x0100_0193);
+            }
+            let segments = parse_template(text);
+            for segment in &segments {
+                if let Segment::Placeholder(name) = segment
+                    && !template.placeholders().contains(&name.as_str())
+                {
+                    errors.push(format!(
+                        "{}: unknown placeholder {{{}}}, expected one of: {}",
+                        path.display(),
+                        name,
+                        template.placeholders().join(", ")
+                    ));
+                }
+            }
+            templates.insert(template, segments);
+        }
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+
+        // Версию задают вручную в version.txt, хэш содержимого отличает
+        // правки, после которых версию забыли поднять
+        let declared = std::fs::read_to_string(dir.join("version.txt"))
+            .map(|v| v.trim().to_string())
+            .unwrap_or_default();
+        let hash = format!("{hash:08x}");
+        let version = if declared.is_empty() {
+            hash
+        } else {
+            format!("{declared}-{hash}")
+        };
+        Ok(PromptSet { version, templates })
+    }
+
+    pub fn version(&self) -> &str {
+        &self.version
+    }
+
+    /// Подставить значения плейсхолдеров в шаблон
+    pub fn render(&self, template: Template, values: &[(&str, &str)]) -> String {
+        let mut result = String::new();
+        for segment in &self.templates[&template] {
+            match segment {
+                Segment::Text(text) => result.push_str(text),
+                Segment::Placeholder(name) => match values.iter().find(|(key, _)| key == name) {
+                    Some((_, value)) => result.push_str(value),
+                    None => {
+                        log::warn!("No value for {{{}}} in {}", name, template.name());
+                    }
+                },
+            }
+        }
+        result
+    }
+}
+
+/// Хэш имен, размеров и времени изменения файлов каталога с окончанием `suffix`
+fn files_version(dir: &Path, suffix: &str) -> u64 {
+    let mut files: Vec<(String, u64, Option<std::time::SystemTime>)> = std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if !name.ends_with(suffix) {
+                return None;
+            }
+            let metadata = entry.metadata().ok()?;
+            Some((name, metadata.len(), metadata.modified().ok()))
+        })
+        .collect();
+    files.sort();
+    let mut hasher = DefaultHasher::new();
+    files.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[derive(Debug)]
+struct StoreState {
+    files_version: u64,
+    prompts: Arc<PromptSet>,
+}
+
+/// Шаблоны с перезагрузкой: при изменении файлов в каталоге новая версия
+/// подхватывается при следующем вопросе. Если новые файлы с ошибками,
+/// остается предыдущая версия.
+#[derive(Debug)]
+pub struct PromptStore {
+    dir: PathBuf,
+    state: Mutex<StoreState>,
+}
+
+impl PromptStore {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, Vec<String>> {
+        let dir = dir.into();
+        let files_version = files_version(&dir, ".txt");
+        let prompts = Arc::new(PromptSet::load(&dir)?);
+        Ok(PromptStore {
+            dir,
+            state: Mutex::new(StoreState {
+                files_version,
+                prompts,
+            }),
+        })
+    }
+
+    /// Актуальные шаблоны. Версия не меняется до конца ответа на вопрос,
+    /// даже если файлы поменяли в процессе.
+    pub fn current(&self) -> Arc<PromptSet> {
+        let files_version = files_version(&self.dir, ".txt");
+        let mut state = self.state.lock().unwrap();
+        if state.files_version != files_version {
+            state.files_version = files_version;
+            match PromptSet::load(&self.dir) {
+                Ok(prompts) => {
+                    log::info!("Reloaded prompt templates, version {}", prompts.version());
+                    state.prompts = Arc::new(prompts);
+                }
+                Err(errors) => log::error!(
+                    "Failed to reload prompt templates, keeping version {}: {}",
+                    state.prompts.version(),
+                    errors.join("; ")
+                ),
+            }
+        }
+        state.prompts.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_prompts() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("prompts_{}", fastrand::u64(..)));
+        std::fs::create_dir_all(&dir).unwrap();
+        for entry in std::fs::read_dir("prompts").unwrap() {
+            let path = entry.unwrap().path();
+            std::fs::copy(&path, dir.join(path.file_name().unwrap())).unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn test_parse_template() {
+        assert_eq!(
+            parse_template("Вопрос: '{question}'. Пример: {\"fields\": [\"cost\"]} {Bad}{}"),
+            vec![
+                Segment::Text("Вопрос: '".to_string()),
+                Segment::Placeholder("question".to_string()),
+                Segment::Text("'. Пример: {\"fields\": [\"cost\"]} {Bad}{}".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_bundled_templates_render() {
+        let prompts = PromptSet::load("prompts").unwrap();
+        let prompt = prompts.render(
+            Template::Program,
+            &[
+                ("program_name", "AI"),
+                ("summary", "Описание"),
+                ("courses", "Python"),
+                ("relevant_info", "cost: 599000"),
+            ],
+        );
+        assert!(prompt.contains("магистратуре AI"));
+        assert!(prompt.contains("cost: 599000"));
+        assert!(!prompt.contains('{'));
+    }
+
+    #[test]
+    fn test_reload_keeps_last_valid_version() {
+        let dir = temp_prompts();
+        let store = PromptStore::new(&dir).unwrap();
+        let first = store.current().version().to_string();
+
+        std::fs::write(dir.join("general.txt"), "Программы: {programs}.").unwrap();
+        let second = store.current();
+        assert_ne!(second.version(), first);
+        assert_eq!(
+            second.render(Template::General, &[("programs", "'AI'")]),
+            "Программы: 'AI'."
+        );
+
+        std::fs::write(dir.join("general.txt"), "Программы: {program}.").unwrap();
+        assert_eq!(store.current().version(), second.version());
+        assert!(PromptSet::load(&dir).unwrap_err()[0].contains("{program}"));
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
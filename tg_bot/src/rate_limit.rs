@@ -0,0 +1,260 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use serde::Deserialize;
+
+use crate::storage::AccessStatus;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RateLimitConfig {
+    /// Сколько вопросов подряд можно задать в одном чате
+    pub chat_burst: u32,
+    /// Скорость восстановления лимита чата, вопросов в минуту
+    pub chat_per_minute: f64,
+    pub global_burst: u32,
+    pub global_per_minute: f64,
+    /// Вопросов в сутки на пользователя, `None` - без ограничения
+    #[serde(deserialize_with = "crate::config::zero_as_none")]
+    pub daily_quota: Option<u32>,
+    pub max_input_chars: usize,
+    /// Отвечать только пользователям из allowlist
+    pub allowlist_only: bool,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            chat_burst: 5,
+            chat_per_minute: 6.0,
+            global_burst: 30,
+            global_per_minute: 120.0,
+            daily_quota: Some(50),
+            max_input_chars: 1000,
+            allowlist_only: false,
+        }
+    }
+}
+
+/// Почему вопрос не будет обработан
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Rejection {
+    Banned,
+    NotAllowlisted,
+    TooLong { max_chars: usize },
+    TooManyRequests,
+    DailyQuotaExceeded { quota: u32 },
+}
+
+impl Rejection {
+    /// Ответ пользователю. Забаненным не отвечаем вовсе.
+    pub fn user_message(&self) -> Option<String> {
+        match self {
+            Rejection::Banned => None,
+            Rejection::NotAllowlisted => Some("Бот сейчас работает в закрытом режиме.".to_string()),
+            Rejection::TooLong { max_chars } => Some(format!(
+                "Вопрос слишком длинный, пожалуйста, сократите его до {max_chars} символов."
+            )),
+            Rejection::TooManyRequests => Some(
+                "Слишком много запросов, пожалуйста, подождите немного и повторите вопрос."
+                    .to_string(),
+            ),
+            Rejection::DailyQuotaExceeded { quota } => Some(format!(
+                "На сегодня лимит вопросов исчерпан ({quota}). Приходите завтра!"
+            )),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_sec: f64,
+    updated: Instant,
+}
+
+impl TokenBucket {
+    fn new(capacity: u32, per_minute: f64) -> Self {
+        TokenBucket {
+            capacity: capacity as f64,
+            tokens: capacity as f64,
+            refill_per_sec: per_minute / 60.0,
+            updated: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self, now: Instant) {
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_sec).min(self.capacity);
+        self.updated = now;
+    }
+
+    fn is_full(&self) -> bool {
+        self.tokens >= self.capacity
+    }
+
+    fn has_token(&self) -> bool {
+        self.tokens >= 1.0
+    }
+
+    fn take(&mut self) {
+        self.tokens -= 1.0;
+    }
+}
+
+/// Ограничение частоты вопросов: token bucket на каждый чат и общий на бота
+#[derive(Debug)]
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    chats: Mutex<HashMap<i64, TokenBucket>>,
+    global: Mutex<TokenBucket>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        let global = TokenBucket::new(config.global_burst, config.global_per_minute);
+        RateLimiter {
+            config,
+            chats: Mutex::new(HashMap::new()),
+            global: Mutex::new(global),
+        }
+    }
+
+    /// Проверить вопрос и, если он проходит, списать токены из лимитов.
+    /// `access` - статус пользователя в allow/ban списке,
+    /// `questions_today` - сколько вопросов пользователь уже задал за сутки.
+    pub fn check(
+        &self,
+        chat_id: i64,
+        text: &str,
+        access: Option<AccessStatus>,
+        questions_today: u32,
+    ) -> Result<(), Rejection> {
+        match access {
+            Some(AccessStatus::Banned) => return Err(Rejection::Banned),
+            Some(AccessStatus::Allowed) => {}
+            None if self.config.allowlist_only => return Err(Rejection::NotAllowlisted),
+            None => {}
+        }
+        if text.chars().count() > self.config.max_input_chars {
+            return Err(Rejection::TooLong {
+                max_chars: self.config.max_input_chars,
+            });
+        }
+        if let Some(quota) = self.config.daily_quota
+            && questions_today >= quota
+        {
+            return Err(Rejection::DailyQuotaExceeded { quota });
+        }
+
+        let now = Instant::now();
+        let mut chats = self.chats.lock().unwrap();
+        if !chats.contains_key(&chat_id) {
+            // Восстановившийся bucket не отличается от нового, его можно забыть
+            chats.retain(|_, chat| {
+                chat.refill(now);
+                !chat.is_full()
+            });
+        }
+        let chat = chats.entry(chat_id).or_insert_with(|| {
+            TokenBucket::new(self.config.chat_burst, self.config.chat_per_minute)
+        });
+        let mut global = self.global.lock().unwrap();
+        chat.refill(now);
+        global.refill(now);
+        // Списываем только если проходят оба лимита, чтобы отказ в одном
+        // не расходовал второй
+        if !chat.has_token() || !global.has_token() {
+            return Err(Rejection::TooManyRequests);
+        }
+        chat.take();
+        global.take();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chat_burst_is_limited() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            chat_burst: 2,
+            chat_per_minute: 0.0,
+            ..RateLimitConfig::default()
+        });
+
+        assert_eq!(limiter.check(1, "q", None, 0), Ok(()));
+        assert_eq!(limiter.check(1, "q", None, 0), Ok(()));
+        assert_eq!(
+            limiter.check(1, "q", None, 0),
+            Err(Rejection::TooManyRequests)
+        );
+        // Другой чат лимитируется отдельно
+        assert_eq!(limiter.check(2, "q", None, 0), Ok(()));
+    }
+
+    #[test]
+    fn test_global_limit_applies_to_all_chats() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            global_burst: 1,
+            global_per_minute: 0.0,
+            ..RateLimitConfig::default()
+        });
+
+        assert_eq!(limiter.check(1, "q", None, 0), Ok(()));
+        assert_eq!(
+            limiter.check(2, "q", None, 0),
+            Err(Rejection::TooManyRequests)
+        );
+    }
+
+    #[test]
+    fn test_access_length_and_quota() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            max_input_chars: 3,
+            daily_quota: Some(1),
+            allowlist_only: true,
+            ..RateLimitConfig::default()
+        });
+
+        assert_eq!(
+            limiter.check(1, "q", Some(AccessStatus::Banned), 0),
+            Err(Rejection::Banned)
+        );
+        assert_eq!(
+            limiter.check(1, "q", None, 0),
+            Err(Rejection::NotAllowlisted)
+        );
+        assert_eq!(
+            limiter.check(1, "вопрос", Some(AccessStatus::Allowed), 0),
+            Err(Rejection::TooLong { max_chars: 3 })
+        );
+        assert_eq!(
+            limiter.check(1, "q", Some(AccessStatus::Allowed), 1),
+            Err(Rejection::DailyQuotaExceeded { quota: 1 })
+        );
+        assert_eq!(
+            limiter.check(1, "q", Some(AccessStatus::Allowed), 0),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn test_idle_chats_are_forgotten() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            chat_burst: 1,
+            chat_per_minute: 60_000.0,
+            ..RateLimitConfig::default()
+        });
+
+        assert_eq!(limiter.check(1, "q", None, 0), Ok(()));
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        assert_eq!(limiter.check(2, "q", None, 0), Ok(()));
+        let chats = limiter.chats.lock().unwrap();
+        assert_eq!(chats.keys().collect::<Vec<_>>(), vec![&2]);
+    }
+}
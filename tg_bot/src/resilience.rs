@@ -0,0 +1,412 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use tokio::sync::watch;
+
+use crate::embeddings::{Embedding, EmbeddingKind, EmbeddingProvider};
+use crate::llm_error::LlmError;
+use crate::llm_provider::{ChatCompletion, ChatMessage, GenerationOptions, LlmProvider};
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ResilienceConfig {
+    /// Сколько раз повторять запрос после первой неудачи
+    pub max_retries: u32,
+    #[serde(rename = "base_delay_ms", deserialize_with = "crate::config::millis")]
+    pub base_delay: Duration,
+    #[serde(rename = "max_delay_ms", deserialize_with = "crate::config::millis")]
+    pub max_delay: Duration,
+    /// Общий лимит времени на запрос вместе со всеми повторами
+    #[serde(rename = "deadline_ms", deserialize_with = "crate::config::millis")]
+    pub deadline: Duration,
+    /// После скольких неудач подряд размыкать цепь
+    pub failure_threshold: u32,
+    /// Сколько держать цепь разомкнутой перед пробным запросом
+    #[serde(rename = "cooldown_secs", deserialize_with = "crate::config::secs")]
+    pub cooldown: Duration,
+}
+
+impl Default for ResilienceConfig {
+    fn default() -> Self {
+        ResilienceConfig {
+            max_retries: 2,
+            base_delay: Duration::from_millis(300),
+            max_delay: Duration::from_secs(5),
+            deadline: Duration::from_secs(60),
+            failure_threshold: 5,
+            cooldown: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ResilienceConfig {
+    /// Экспоненциальная задержка с полным джиттером: случайное значение
+    /// от 0 до `base_delay * 2^attempt`, но не больше `max_delay`
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exp = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+        exp.mul_f64(fastrand::f64())
+    }
+}
+
+#[derive(Debug)]
+enum BreakerState {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    /// Пропущен один пробный запрос, ждем его результата. Если пробный
+    /// запрос отменили, через `cooldown` пропускаем следующий.
+    HalfOpen {
+        since: Instant,
+    },
+}
+
+#[derive(Debug)]
+struct CircuitBreaker {
+    state: Mutex<BreakerState>,
+    failure_threshold: u32,
+    cooldown: Duration,
+}
+
+impl CircuitBreaker {
+    fn new(failure_threshold: u32, cooldown: Duration) -> Self {
+        CircuitBreaker {
+            state: Mutex::new(BreakerState::Closed { failures: 0 }),
+            failure_threshold,
+            cooldown,
+        }
+    }
+
+    fn allow(&self) -> bool {
+        let mut state = self.state.lock().unwrap();
+        let now = Instant::now();
+        match *state {
+            BreakerState::Closed { .. } => true,
+            BreakerState::Open { until } if now >= until => {
+                *state = BreakerState::HalfOpen { since: now };
+                true
+            }
+            BreakerState::HalfOpen { since } if now >= since + self.cooldown => {
+                *state = BreakerState::HalfOpen { since: now };
+                true
+            }
+            BreakerState::Open { .. } | BreakerState::HalfOpen { .. } => false,
+        }
+    }
+
+    fn on_success(&self) {
+        *self.state.lock().unwrap() = BreakerState::Closed { failures: 0 };
+    }
+
+    /// `name` - имя провайдера для логов
+    fn on_failure(&self, name: &str) {
+        let mut state = self.state.lock().unwrap();
+        let failures = match *state {
+            BreakerState::Closed { failures } => failures + 1,
+            BreakerState::Open { .. } | BreakerState::HalfOpen { .. } => self.failure_threshold,
+        };
+        *state = if failures >= self.failure_threshold {
+            log::warn!(
+                "Circuit breaker for {} opened for {:?}",
+                name,
+                self.cooldown
+            );
+            BreakerState::Open {
+                until: Instant::now() + self.cooldown,
+            }
+        } else {
+            BreakerState::Closed { failures }
+        };
+    }
+}
+
+/// Обертка над провайдером: повторы с экспоненциальной задержкой,
+/// общий дедлайн на запрос и circuit breaker, который при недоступности
+/// провайдера сразу возвращает `LlmError::CircuitOpen`. Оборачивает
+/// и `LlmProvider`, и `EmbeddingProvider`.
+pub struct ResilientProvider<P: ?Sized = dyn LlmProvider> {
+    inner: Arc<P>,
+    config: ResilienceConfig,
+    breaker: CircuitBreaker,
+}
+
+impl<P: ?Sized> ResilientProvider<P> {
+    pub fn new(inner: Arc<P>, config: ResilienceConfig) -> Self {
+        let breaker = CircuitBreaker::new(config.failure_threshold, config.cooldown);
+        ResilientProvider {
+            inner,
+            config,
+            breaker,
+        }
+    }
+
+    /// Вызвать `attempt_fn` с повторами, `name` - имя провайдера для логов
+    async fn call<'a, T, F, Fut>(&'a self, name: &str, attempt_fn: F) -> Result<T, LlmError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<T, LlmError>> + 'a,
+    {
+        if !self.breaker.allow() {
+            return Err(LlmError::CircuitOpen);
+        }
+
+        let deadline = Instant::now() + self.config.deadline;
+        let mut attempt = 0;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            let result = match tokio::time::timeout(remaining, attempt_fn()).await {
+                Ok(result) => result,
+                Err(_) => Err(LlmError::DeadlineExceeded(self.config.deadline)),
+            };
+
+            let err = match result {
+                Ok(value) => {
+                    self.breaker.on_success();
+                    return Ok(value);
+                }
+                Err(err) => err,
+            };
+            if !err.is_retryable() {
+                // Ошибка запроса, а не провайдера: провайдер ответил, значит он доступен
+                self.breaker.on_success();
+                return Err(err);
+            }
+
+            let delay = match &err {
+                LlmError::RateLimited {
+                    retry_after: Some(retry_after),
+                    ..
+                } => *retry_after,
+                _ => self.config.backoff(attempt),
+            };
+            if attempt >= self.config.max_retries || Instant::now() + delay >= deadline {
+                self.breaker.on_failure(name);
+                return Err(err);
+            }
+
+            attempt += 1;
+            log::warn!(
+                "Retrying {} (attempt {}) in {:?} after error: {}",
+                name,
+                attempt,
+                delay,
+                err
+            );
+            tokio::time::sleep(delay).await;
+        }
+    }
+}
+
+#[async_trait]
+impl<P: LlmProvider + ?Sized> LlmProvider for ResilientProvider<P> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    async fn complete(
+        &self,
+        messages: &[ChatMessage],
+        options: &GenerationOptions,
+    ) -> Result<ChatCompletion, LlmError> {
+        self.call(self.inner.name(), || self.inner.complete(messages, options))
+            .await
+    }
+
+    async fn complete_stream(
+        &self,
+        messages: &[ChatMessage],
+        options: &GenerationOptions,
+        partial: &watch::Sender<String>,
+    ) -> Result<ChatCompletion, LlmError> {
+        self.call(self.inner.name(), || {
+            self.inner.complete_stream(messages, options, partial)
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl<P: EmbeddingProvider + ?Sized> EmbeddingProvider for ResilientProvider<P> {
+    fn name(&self) -> &str {
+        self.inner.name()
+    }
+
+    fn model(&self) -> &str {
+        self.inner.model()
+    }
+
+    async fn embed(&self, text: &str, kind: EmbeddingKind) -> Result<Embedding, LlmError> {
+        self.call(self.inner.name(), || self.inner.embed(text, kind))
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_provider::TokenUsage;
+    use reqwest::StatusCode;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Провайдер, который первые `failures` вызовов отвечает 503,
+    /// а вызов номер `hang_on` не завершается никогда
+    struct FlakyProvider {
+        failures: u32,
+        hang_on: Option<u32>,
+        calls: AtomicU32,
+    }
+
+    #[async_trait]
+    impl LlmProvider for FlakyProvider {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        fn model(&self) -> &str {
+            "test"
+        }
+
+        async fn complete(
+            &self,
+            _messages: &[ChatMessage],
+            _options: &GenerationOptions,
+        ) -> Result<ChatCompletion, LlmError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            if self.hang_on == Some(call) {
+                std::future::pending::<()>().await;
+            }
+            if call < self.failures {
+                Err(LlmError::Server {
+                    status: StatusCode::SERVICE_UNAVAILABLE,
+                    body: String::new(),
+                })
+            } else {
+                Ok(ChatCompletion {
+                    text: "ok".to_string(),
+                    usage: TokenUsage::default(),
+                    model: "test".to_string(),
+                })
+            }
+        }
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for FlakyProvider {
+        fn name(&self) -> &str {
+            "flaky"
+        }
+
+        fn model(&self) -> &str {
+            "test"
+        }
+
+        async fn embed(&self, _text: &str, _kind: EmbeddingKind) -> Result<Embedding, LlmError> {
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            if call < self.failures {
+                Err(LlmError::Server {
+                    status: StatusCode::SERVICE_UNAVAILABLE,
+                    body: String::new(),
+                })
+            } else {
+                Ok(Embedding {
+                    vector: vec![1.0],
+                    tokens: 3,
+                })
+            }
+        }
+    }
+
+    fn config() -> ResilienceConfig {
+        ResilienceConfig {
+            max_retries: 2,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(5),
+            deadline: Duration::from_secs(5),
+            failure_threshold: 1,
+            cooldown: Duration::from_secs(60),
+        }
+    }
+
+    async fn ask(provider: &dyn LlmProvider) -> Result<ChatCompletion, LlmError> {
+        let messages = [ChatMessage::system("system"), ChatMessage::user("user")];
+        provider
+            .complete(&messages, &GenerationOptions::default())
+            .await
+    }
+
+    fn flaky(failures: u32) -> Arc<FlakyProvider> {
+        Arc::new(FlakyProvider {
+            failures,
+            hang_on: None,
+            calls: AtomicU32::new(0),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_retries_transient_errors() {
+        let inner = flaky(2);
+        let provider = ResilientProvider::new(inner.clone(), config());
+
+        let answer = ask(&provider).await.unwrap();
+        assert_eq!(answer.text, "ok");
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_circuit_opens_after_exhausted_retries() {
+        let inner = flaky(10);
+        let provider = ResilientProvider::new(inner.clone(), config());
+
+        assert!(matches!(ask(&provider).await, Err(LlmError::Server { .. })));
+        assert!(matches!(ask(&provider).await, Err(LlmError::CircuitOpen)));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_retries_query_embeddings() {
+        let inner = flaky(2);
+        let provider = ResilientProvider::new(inner.clone(), config());
+
+        let embedding = provider.embed("вопрос", EmbeddingKind::Query).await;
+        assert_eq!(embedding.unwrap().tokens, 3);
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_probe_does_not_block_circuit() {
+        let inner = Arc::new(FlakyProvider {
+            failures: 1,
+            hang_on: Some(1),
+            calls: AtomicU32::new(0),
+        });
+        let cooldown = Duration::from_millis(50);
+        let config = ResilienceConfig {
+            max_retries: 0,
+            cooldown,
+            ..config()
+        };
+        let provider = ResilientProvider::new(inner.clone(), config);
+
+        assert!(matches!(ask(&provider).await, Err(LlmError::Server { .. })));
+        tokio::time::sleep(cooldown).await;
+        // Пробный запрос отменили, не дождавшись ответа
+        let probe = tokio::time::timeout(Duration::from_millis(10), ask(&provider)).await;
+        assert!(probe.is_err());
+        assert!(matches!(ask(&provider).await, Err(LlmError::CircuitOpen)));
+
+        tokio::time::sleep(cooldown).await;
+        assert_eq!(ask(&provider).await.unwrap().text, "ok");
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+    }
+}
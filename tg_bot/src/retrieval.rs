@@ -0,0 +1,215 @@
+use std::collections::HashMap;
+
+use crate::chunker::Chunk;
+use crate::normalize;
+
+/// Параметры BM25, обычные значения для коротких текстов
+const K1: f32 = 1.2;
+const B: f32 = 0.75;
+
+/// Слова, которые есть почти в любом вопросе и ничего не говорят о его теме
+#[rustfmt::skip]
+const STOP_WORDS: &[&str] = &[
+    // русские
+    "а", "без", "бы", "был", "была", "были", "быть", "в", "вам", "вас", "весь", "во", "вот",
+    "все", "всех", "вы", "где", "да", "для", "до", "его", "ее", "если", "есть", "еще", "же",
+    "за", "и", "из", "или", "им", "их", "к", "как", "какая", "какие", "каким", "какой", "когда",
+    "кто", "ли", "либо", "мне", "можно", "мой", "мы", "на", "над", "не", "нет", "ни", "но", "о",
+    "об", "от", "по", "под", "при", "про", "с", "со", "так", "также", "то", "только", "у",
+    "уже", "чем", "что", "чтобы", "эта", "эти", "это", "этой", "я", "расскажи", "расскажите",
+    "скажи", "скажите", "подскажи", "подскажите", "пожалуйста", "хочу",
+    // английские
+    "a", "an", "and", "are", "as", "at", "be", "by", "can", "do", "does", "for", "from", "how",
+    "i", "in", "is", "it", "of", "on", "or", "the", "to", "what", "which", "with", "you",
+];
+
+/// Основы слов текста для поиска, без стоп-слов, синонимы приведены
+/// к одной форме, см. `normalize::tokens`
+pub fn tokenize(text: &str) -> Vec<String> {
+    normalize::tokens(text)
+        .into_iter()
+        .filter(|token| !STOP_WORDS.contains(&token.word.as_str()))
+        .map(|token| token.stem)
+        .collect()
+}
+
+/// Найденный фрагмент
+#[derive(Debug, Clone, Copy)]
+pub struct Hit<'a> {
+    pub chunk: &'a Chunk,
+    pub score: f32,
+    /// Доля слов вопроса, которые есть во фрагменте, с весом по редкости слова:
+    /// частые слова вроде названия программы значат меньше
+    pub coverage: f32,
+}
+
+/// Инвертированный индекс BM25 по фрагментам всех программ
+#[derive(Debug, Clone, Default)]
+pub struct SearchIndex {
+    chunks: Vec<Chunk>,
+    /// Слово -> (номер фрагмента, сколько раз слово в нем встречается)
+    postings: HashMap<String, Vec<(usize, u32)>>,
+    lengths: Vec<usize>,
+    avg_length: f32,
+}
+
+impl SearchIndex {
+    pub fn new(chunks: Vec<Chunk>) -> Self {
+        let mut postings: HashMap<String, Vec<(usize, u32)>> = HashMap::new();
+        let mut lengths = Vec::with_capacity(chunks.len());
+        // Подпись фрагмента тоже ищется: вопрос о цене находит "Стоимость обучения"
+        for (id, chunk) in chunks.iter().enumerate() {
+            let tokens = tokenize(&chunk.render());
+            lengths.push(tokens.len());
+            let mut counts: HashMap<String, u32> = HashMap::new();
+            for token in tokens {
+                *counts.entry(token).or_default() += 1;
+            }
+            for (token, count) in counts {
+                postings.entry(token).or_default().push((id, count));
+            }
+        }
+        let avg_length = if lengths.is_empty() {
+            0.0
+        } else {
+            lengths.iter().sum::<usize>() as f32 / lengths.len() as f32
+        };
+        SearchIndex {
+            chunks,
+            postings,
+            lengths,
+            avg_length,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    /// Все фрагменты в порядке программ и полей
+    pub fn chunks(&self) -> &[Chunk] {
+        &self.chunks
+    }
+
+    /// До `limit` фрагментов, подходящих под `filter`, по убыванию оценки.
+    /// Фрагменты без общих с вопросом слов не возвращаются.
+    pub fn search(
+        &self,
+        query: &str,
+        limit: usize,
+        filter: impl Fn(&Chunk) -> bool,
+    ) -> Vec<Hit<'_>> {
+        let mut terms = tokenize(query);
+        terms.sort();
+        terms.dedup();
+        if terms.is_empty() {
+            return Vec::new();
+        }
+
+        let total = self.chunks.len() as f32;
+        let idf = |df: f32| ((total - df + 0.5) / (df + 0.5) + 1.0).ln();
+        // Слова, которых нет в данных, получают наибольший вес
+        let total_weight: f32 = terms
+            .iter()
+            .map(|term| idf(self.postings.get(term).map_or(0, Vec::len) as f32))
+            .sum();
+        let mut scores: HashMap<usize, (f32, f32)> = HashMap::new();
+        for term in &terms {
+            let Some(postings) = self.postings.get(term) else {
+                continue;
+            };
+            let idf = idf(postings.len() as f32);
+            for &(id, tf) in postings {
+                if !filter(&self.chunks[id]) {
+                    continue;
+                }
+                let tf = tf as f32;
+                let norm = 1.0 - B + B * self.lengths[id] as f32 / self.avg_length;
+                let entry = scores.entry(id).or_default();
+                entry.0 += idf * tf * (K1 + 1.0) / (tf + K1 * norm);
+                entry.1 += idf;
+            }
+        }
+
+        let mut scores: Vec<(usize, (f32, f32))> = scores.into_iter().collect();
+        // При равной оценке порядок как в данных, чтобы ответы не менялись от запуска к запуску
+        scores.sort_by(|(a_id, (a, _)), (b_id, (b, _))| b.total_cmp(a).then(a_id.cmp(b_id)));
+        let mut hits: Vec<Hit> = scores
+            .into_iter()
+            .map(|(id, (score, matched))| Hit {
+                chunk: &self.chunks[id],
+                score,
+                coverage: matched / total_weight,
+            })
+            .collect();
+        hits.truncate(limit);
+        hits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chunk(field: &str, index: usize, label: &str, text: &str) -> Chunk {
+        Chunk {
+            id: format!("ai/{field}[{index}]"),
+            program: "ai".to_string(),
+            field: field.to_string(),
+            index: Some(index),
+            label: label.to_string(),
+            text: text.to_string(),
+            url: "https://abit.itmo.ru/program/master/ai".to_string(),
+            section: "Основные сведения".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("Какие курсы по обработке естественного языка, и где учёба?"),
+            vec!["курс", "nlp", "учеб"]
+        );
+    }
+
+    #[test]
+    fn test_search_ranks_matching_chunks() {
+        let index = SearchIndex::new(vec![
+            chunk("courses", 0, "Курс", "Рекомендательные системы"),
+            chunk("courses", 1, "Курс", "Системы управления базами данных"),
+            chunk("courses", 2, "Курс", "Глубокое обучение"),
+            chunk(
+                "faq",
+                0,
+                "Частый вопрос",
+                "Есть ли общежитие? Иногородним предоставляется общежитие",
+            ),
+            chunk("cost", 0, "Стоимость обучения", "599 000 ₽"),
+        ]);
+
+        let hits = index.search("рекомендательные системы", 5, |_| {
+            true
+        });
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].chunk.text, "Рекомендательные системы");
+        assert_eq!(hits[0].coverage, 1.0);
+        // "рекомендательные" реже, чем "системы", и весит больше
+        assert!(hits[1].coverage < 0.5);
+
+        let hits = index.search("Дают ли общежитие?", 5, |c| c.field == "faq");
+        assert_eq!(hits[0].chunk.field, "faq");
+        assert!(
+            index
+                .search("общежитие", 5, |c| c.field == "courses")
+                .is_empty()
+        );
+        assert!(index.search("и на по", 5, |_| true).is_empty());
+        // Поле находится по подписи, которой нет в тексте
+        let hits = index.search("Какая стоимость?", 5, |_| true);
+        assert_eq!(hits[0].chunk.id, "ai/cost[0]");
+    }
+}
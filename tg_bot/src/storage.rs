@@ -0,0 +1,681 @@
+use std::path::Path;
+use std::sync::Mutex;
+
+use rusqlite::{Connection, OptionalExtension, params};
+
+use crate::dialogue::Turn;
+use crate::llm_provider::TokenUsage;
+
+/// Миграции схемы. Номер миграции хранится в `PRAGMA user_version`,
+/// новые миграции добавляются только в конец списка.
+const MIGRATIONS: &[&str] = &[
+    // 1: чаты и сообщения
+    "CREATE TABLE chats (
+        id INTEGER PRIMARY KEY,
+        username TEXT,
+        first_seen TEXT NOT NULL DEFAULT (datetime('now')),
+        last_seen TEXT NOT NULL DEFAULT (datetime('now')),
+        reset_after_message INTEGER
+    );
+    CREATE TABLE messages (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        chat_id INTEGER NOT NULL REFERENCES chats(id),
+        created_at TEXT NOT NULL DEFAULT (datetime('now')),
+        question TEXT NOT NULL,
+        answer TEXT,
+        program TEXT,
+        relevant_fields TEXT NOT NULL DEFAULT '[]',
+        input_tokens INTEGER NOT NULL DEFAULT 0,
+        completion_tokens INTEGER NOT NULL DEFAULT 0,
+        total_tokens INTEGER NOT NULL DEFAULT 0,
+        latency_ms INTEGER NOT NULL DEFAULT 0,
+        error TEXT
+    );
+    CREATE INDEX messages_chat_id ON messages(chat_id, id);
+    CREATE INDEX messages_created_at ON messages(created_at);",
+    // 2: стоимость ответов и отдельные вызовы LLM
+    "ALTER TABLE messages ADD COLUMN cost REAL NOT NULL DEFAULT 0;
+    CREATE TABLE llm_calls (
+        id INTEGER PRIMARY KEY AUTOINCREMENT,
+        message_id INTEGER NOT NULL REFERENCES messages(id),
+        created_at TEXT NOT NULL DEFAULT (datetime('now')),
+        purpose TEXT NOT NULL,
+        model TEXT NOT NULL,
+        input_tokens INTEGER NOT NULL,
+        completion_tokens INTEGER NOT NULL,
+        total_tokens INTEGER NOT NULL,
+        cost REAL NOT NULL
+    );
+    CREATE INDEX llm_calls_message_id ON llm_calls(message_id);",
+    // 3: автор вопроса для дневных квот и списки доступа
+    "ALTER TABLE messages ADD COLUMN user_id INTEGER;
+    CREATE INDEX messages_user_id ON messages(user_id, created_at);
+    CREATE TABLE access_list (
+        user_id INTEGER PRIMARY KEY,
+        status TEXT NOT NULL,
+        updated_by INTEGER,
+        updated_at TEXT NOT NULL DEFAULT (datetime('now'))
+    );",
+    // 4: ответы из кэша
+    "ALTER TABLE llm_calls ADD COLUMN cached INTEGER NOT NULL DEFAULT 0;",
+    // 5: неразобранные структурированные ответы модели
+    "ALTER TABLE messages ADD COLUMN parse_failures INTEGER NOT NULL DEFAULT 0;",
+    // 6: результат классификации вопроса
+    "ALTER TABLE messages ADD COLUMN intent TEXT;
+    ALTER TABLE messages ADD COLUMN topic TEXT;",
+    // 7: версия шаблонов промптов, с которыми получен ответ
+    "ALTER TABLE messages ADD COLUMN prompt_version TEXT;",
+    // 8: значения из ответа, которых нет в данных программы, json-массив
+    "ALTER TABLE messages ADD COLUMN unverified_facts TEXT;",
+];
+
+/// Запись об одном вопросе пользователя и ответе бота
+#[derive(Debug, Clone, Default)]
+pub struct ExchangeRecord {
+    pub chat_id: i64,
+    /// Автор вопроса, в личных чатах совпадает с `chat_id`
+    pub user_id: Option<u64>,
+    pub question: String,
+    pub answer: Option<String>,
+    pub program: Option<String>,
+    pub relevant_fields: Vec<String>,
+    pub usage: TokenUsage,
+    pub cost: f64,
+    pub calls: Vec<LlmCallRecord>,
+    pub latency_ms: u64,
+    /// Сколько ответов модели не удалось разобрать как JSON
+    pub parse_failures: u32,
+    /// Намерение и тема вопроса по классификатору
+    pub intent: Option<String>,
+    pub topic: Option<String>,
+    /// Версия шаблонов промптов, см. `prompts::PromptSet::version`
+    pub prompt_version: Option<String>,
+    /// Числа, даты и адреса из ответа модели, которых нет в данных программы
+    pub unverified_facts: Vec<String>,
+    pub error: Option<String>,
+}
+
+/// Один вызов LLM в рамках ответа на вопрос
+#[derive(Debug, Clone)]
+pub struct LlmCallRecord {
+    /// Зачем вызывали модель: `field_selection`, `answer`, `embedding` и т.п.
+    pub purpose: String,
+    pub model: String,
+    /// Для ответа из кэша: сколько токенов стоил исходный вызов
+    pub usage: TokenUsage,
+    pub cost: f64,
+    /// Ответ взят из кэша, модель не вызывалась
+    pub cached: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredExchange {
+    pub id: i64,
+    pub chat_id: i64,
+    pub created_at: String,
+    pub question: String,
+    pub answer: Option<String>,
+    pub program: Option<String>,
+    pub intent: Option<String>,
+    pub topic: Option<String>,
+    pub prompt_version: Option<String>,
+    pub relevant_fields: Vec<String>,
+    pub unverified_facts: Vec<String>,
+    pub usage: TokenUsage,
+    pub cost: f64,
+    pub latency_ms: u64,
+    pub error: Option<String>,
+}
+
+/// Последние реплики чата для восстановления истории диалога
+#[derive(Debug, Clone)]
+pub struct DialogueTail {
+    pub chat_id: i64,
+    pub turns: Vec<Turn>,
+}
+
+/// Агрегаты по сообщениям за период
+#[derive(Debug, Clone, Default)]
+pub struct UsageSummary {
+    pub chats: u64,
+    pub messages: u64,
+    pub errors: u64,
+    pub parse_failures: u64,
+    pub usage: TokenUsage,
+    pub cost: f64,
+    pub avg_latency_ms: f64,
+}
+
+/// Расход за календарный период (UTC)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Today,
+    ThisMonth,
+}
+
+impl Period {
+    fn start_modifier(self) -> &'static str {
+        match self {
+            Period::Today => "start of day",
+            Period::ThisMonth => "start of month",
+        }
+    }
+}
+
+/// Статус пользователя в списке доступа, который ведут администраторы
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccessStatus {
+    Allowed,
+    Banned,
+}
+
+impl AccessStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            AccessStatus::Allowed => "allowed",
+            AccessStatus::Banned => "banned",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "allowed" => Some(AccessStatus::Allowed),
+            "banned" => Some(AccessStatus::Banned),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Spending {
+    pub questions: u64,
+    pub usage: TokenUsage,
+    pub cost: f64,
+    /// Сколько вызовов LLM заменил кэш и сколько токенов это сэкономило
+    pub cache_hits: u64,
+    pub saved_tokens: u64,
+}
+
+/// Хранилище диалогов и аналитики во встроенной SQLite
+pub struct Storage {
+    conn: Mutex<Connection>,
+}
+
+impl Storage {
+    pub fn open(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        if let Some(parent) = path.as_ref().parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        Self::from_connection(Connection::open(path)?)
+    }
+
+    pub fn open_in_memory() -> anyhow::Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?)
+    }
+
+    fn from_connection(mut conn: Connection) -> anyhow::Result<Self> {
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        conn.pragma_update(None, "foreign_keys", "ON")?;
+        migrate(&mut conn)?;
+        Ok(Storage {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    /// Зарегистрировать чат или обновить время последней активности
+    pub fn touch_chat(&self, chat_id: i64, username: Option<&str>) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT INTO chats (id, username) VALUES (?1, ?2)
+             ON CONFLICT(id) DO UPDATE SET
+                username = COALESCE(excluded.username, chats.username),
+                last_seen = datetime('now')",
+            params![chat_id, username],
+        )?;
+        Ok(())
+    }
+
+    /// Отметить сброс контекста, чтобы история до этого момента не восстанавливалась
+    pub fn reset_chat(&self, chat_id: i64) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE chats SET reset_after_message =
+                (SELECT MAX(id) FROM messages WHERE chat_id = ?1)
+             WHERE id = ?1",
+            params![chat_id],
+        )?;
+        Ok(())
+    }
+
+    pub fn record_exchange(&self, record: &ExchangeRecord) -> anyhow::Result<i64> {
+        let mut conn = self.conn.lock().unwrap();
+        let tx = conn.transaction()?;
+        tx.execute(
+            "INSERT INTO messages (
+                chat_id, user_id, question, answer, program, relevant_fields,
+                input_tokens, completion_tokens, total_tokens, cost, latency_ms,
+                parse_failures, intent, topic, prompt_version, unverified_facts, error
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17)",
+            params![
+                record.chat_id,
+                record.user_id,
+                record.question,
+                record.answer,
+                record.program,
+                serde_json::to_string(&record.relevant_fields)?,
+                record.usage.input_tokens,
+                record.usage.completion_tokens,
+                record.usage.total_tokens,
+                record.cost,
+                record.latency_ms,
+                record.parse_failures,
+                record.intent,
+                record.topic,
+                record.prompt_version,
+                // NULL, если все проверено, чтобы такие ответы было легко найти
+                (!record.unverified_facts.is_empty())
+                    .then(|| serde_json::to_string(&record.unverified_facts))
+                    .transpose()?,
+                record.error,
+            ],
+        )?;
+        let message_id = tx.last_insert_rowid();
+        for call in &record.calls {
+            tx.execute(
+                "INSERT INTO llm_calls (
+                    message_id, purpose, model, input_tokens, completion_tokens, total_tokens,
+                    cost, cached
+                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+                params![
+                    message_id,
+                    call.purpose,
+                    call.model,
+                    call.usage.input_tokens,
+                    call.usage.completion_tokens,
+                    call.usage.total_tokens,
+                    call.cost,
+                    call.cached,
+                ],
+            )?;
+        }
+        tx.commit()?;
+        Ok(message_id)
+    }
+
+    /// Сколько вопросов, токенов и денег потрачено с начала дня или месяца
+    pub fn spending(&self, period: Period) -> anyhow::Result<Spending> {
+        let conn = self.conn.lock().unwrap();
+        let mut spending = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(input_tokens), 0), COALESCE(SUM(completion_tokens), 0),
+                    COALESCE(SUM(total_tokens), 0), COALESCE(SUM(cost), 0)
+             FROM messages WHERE created_at >= datetime('now', ?1)",
+            params![period.start_modifier()],
+            |row| {
+                Ok(Spending {
+                    questions: row.get(0)?,
+                    usage: TokenUsage {
+                        input_tokens: row.get(1)?,
+                        completion_tokens: row.get(2)?,
+                        total_tokens: row.get(3)?,
+                    },
+                    cost: row.get(4)?,
+                    ..Spending::default()
+                })
+            },
+        )?;
+        (spending.cache_hits, spending.saved_tokens) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(total_tokens), 0)
+             FROM llm_calls WHERE cached AND created_at >= datetime('now', ?1)",
+            params![period.start_modifier()],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        Ok(spending)
+    }
+
+    /// Сколько вопросов пользователь задал с начала суток (UTC)
+    pub fn questions_today(&self, user_id: u64) -> anyhow::Result<u32> {
+        let conn = self.conn.lock().unwrap();
+        let count = conn.query_row(
+            "SELECT COUNT(*) FROM messages
+             WHERE user_id = ?1 AND created_at >= datetime('now', 'start of day')",
+            params![user_id],
+            |row| row.get(0),
+        )?;
+        Ok(count)
+    }
+
+    pub fn access_status(&self, user_id: u64) -> anyhow::Result<Option<AccessStatus>> {
+        let conn = self.conn.lock().unwrap();
+        let status: Option<String> = conn
+            .query_row(
+                "SELECT status FROM access_list WHERE user_id = ?1",
+                params![user_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(status.as_deref().and_then(AccessStatus::parse))
+    }
+
+    /// Внести пользователя в список доступа или убрать из него (`None`)
+    pub fn set_access(
+        &self,
+        user_id: u64,
+        status: Option<AccessStatus>,
+        updated_by: u64,
+    ) -> anyhow::Result<()> {
+        let conn = self.conn.lock().unwrap();
+        match status {
+            Some(status) => conn.execute(
+                "INSERT INTO access_list (user_id, status, updated_by) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(user_id) DO UPDATE SET
+                    status = excluded.status,
+                    updated_by = excluded.updated_by,
+                    updated_at = datetime('now')",
+                params![user_id, status.as_str(), updated_by],
+            )?,
+            None => conn.execute(
+                "DELETE FROM access_list WHERE user_id = ?1",
+                params![user_id],
+            )?,
+        };
+        Ok(())
+    }
+
+    pub fn access_list(&self) -> anyhow::Result<Vec<(u64, AccessStatus)>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt =
+            conn.prepare("SELECT user_id, status FROM access_list ORDER BY status, user_id")?;
+        let rows = stmt.query_map([], |row| {
+            Ok((row.get::<_, u64>(0)?, row.get::<_, String>(1)?))
+        })?;
+        let mut list = Vec::new();
+        for row in rows {
+            let (user_id, status) = row?;
+            if let Some(status) = AccessStatus::parse(&status) {
+                list.push((user_id, status));
+            }
+        }
+        Ok(list)
+    }
+
+    /// Последние сообщения чата, от новых к старым
+    pub fn recent_exchanges(
+        &self,
+        chat_id: i64,
+        limit: usize,
+    ) -> anyhow::Result<Vec<StoredExchange>> {
+        self.query_exchanges(
+            "SELECT * FROM messages WHERE chat_id = ?1 ORDER BY id DESC LIMIT ?2",
+            params![chat_id, limit],
+        )
+    }
+
+    /// Последние сообщения, завершившиеся ошибкой, от новых к старым
+    pub fn recent_errors(&self, limit: usize) -> anyhow::Result<Vec<StoredExchange>> {
+        self.query_exchanges(
+            "SELECT * FROM messages WHERE error IS NOT NULL ORDER BY id DESC LIMIT ?1",
+            params![limit],
+        )
+    }
+
+    /// Последние ответы с непроверенными значениями для разбора, от новых к старым
+    pub fn recent_unverified(&self, limit: usize) -> anyhow::Result<Vec<StoredExchange>> {
+        self.query_exchanges(
+            "SELECT * FROM messages WHERE unverified_facts IS NOT NULL ORDER BY id DESC LIMIT ?1",
+            params![limit],
+        )
+    }
+
+    /// Успешные обмены репликами после последнего сброса контекста,
+    /// не больше `turns` на чат, от старых к новым. Используется для
+    /// восстановления истории диалогов после перезапуска.
+    pub fn dialogue_tails(&self, turns: usize) -> anyhow::Result<Vec<DialogueTail>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT chat_id, question, answer FROM (
+                SELECT m.chat_id, m.id, m.question, m.answer,
+                       ROW_NUMBER() OVER (PARTITION BY m.chat_id ORDER BY m.id DESC) AS rn
+                FROM messages m JOIN chats c ON c.id = m.chat_id
+                WHERE m.answer IS NOT NULL AND m.error IS NULL
+                  AND m.id > COALESCE(c.reset_after_message, 0)
+             )
+             WHERE rn <= ?1
+             ORDER BY chat_id, id",
+        )?;
+        let rows = stmt.query_map(params![turns], |row| {
+            Ok((
+                row.get::<_, i64>(0)?,
+                row.get::<_, String>(1)?,
+                row.get::<_, String>(2)?,
+            ))
+        })?;
+
+        let mut tails: Vec<DialogueTail> = Vec::new();
+        for row in rows {
+            let (chat_id, question, answer) = row?;
+            let turn = Turn { question, answer };
+            match tails.last_mut() {
+                Some(tail) if tail.chat_id == chat_id => tail.turns.push(turn),
+                _ => tails.push(DialogueTail {
+                    chat_id,
+                    turns: vec![turn],
+                }),
+            }
+        }
+        Ok(tails)
+    }
+
+    /// Сводка за последние `days` дней
+    pub fn usage_summary(&self, days: u32) -> anyhow::Result<UsageSummary> {
+        let conn = self.conn.lock().unwrap();
+        let summary = conn
+            .query_row(
+                "SELECT COUNT(DISTINCT chat_id), COUNT(*), COUNT(error),
+                        COALESCE(SUM(input_tokens), 0), COALESCE(SUM(completion_tokens), 0),
+                        COALESCE(SUM(total_tokens), 0), COALESCE(SUM(cost), 0),
+                        COALESCE(AVG(latency_ms), 0), COALESCE(SUM(parse_failures), 0)
+                 FROM messages WHERE created_at >= datetime('now', ?1)",
+                params![format!("-{days} days")],
+                |row| {
+                    Ok(UsageSummary {
+                        chats: row.get(0)?,
+                        messages: row.get(1)?,
+                        errors: row.get(2)?,
+                        usage: TokenUsage {
+                            input_tokens: row.get(3)?,
+                            completion_tokens: row.get(4)?,
+                            total_tokens: row.get(5)?,
+                        },
+                        cost: row.get(6)?,
+                        avg_latency_ms: row.get(7)?,
+                        parse_failures: row.get(8)?,
+                    })
+                },
+            )
+            .optional()?;
+        Ok(summary.unwrap_or_default())
+    }
+
+    fn query_exchanges(
+        &self,
+        sql: &str,
+        params: impl rusqlite::Params,
+    ) -> anyhow::Result<Vec<StoredExchange>> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(sql)?;
+        let rows = stmt.query_map(params, |row| {
+            let relevant_fields: String = row.get("relevant_fields")?;
+            let unverified_facts: Option<String> = row.get("unverified_facts")?;
+            Ok(StoredExchange {
+                id: row.get("id")?,
+                chat_id: row.get("chat_id")?,
+                created_at: row.get("created_at")?,
+                question: row.get("question")?,
+                answer: row.get("answer")?,
+                program: row.get("program")?,
+                intent: row.get("intent")?,
+                topic: row.get("topic")?,
+                prompt_version: row.get("prompt_version")?,
+                relevant_fields: serde_json::from_str(&relevant_fields).unwrap_or_default(),
+                unverified_facts: unverified_facts
+                    .and_then(|facts| serde_json::from_str(&facts).ok())
+                    .unwrap_or_default(),
+                usage: TokenUsage {
+                    input_tokens: row.get("input_tokens")?,
+                    completion_tokens: row.get("completion_tokens")?,
+                    total_tokens: row.get("total_tokens")?,
+                },
+                cost: row.get("cost")?,
+                latency_ms: row.get("latency_ms")?,
+                error: row.get("error")?,
+            })
+        })?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+}
+
+fn migrate(conn: &mut Connection) -> anyhow::Result<()> {
+    let version: usize = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(version) {
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration)?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+        log::info!("Applied storage migration {}", index + 1);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn exchange(chat_id: i64, question: &str, answer: Option<&str>) -> ExchangeRecord {
+        ExchangeRecord {
+            chat_id,
+            user_id: Some(chat_id as u64),
+            question: question.to_string(),
+            answer: answer.map(str::to_string),
+            program: Some("ai".to_string()),
+            relevant_fields: vec!["cost".to_string()],
+            usage: TokenUsage {
+                input_tokens: 10,
+                completion_tokens: 5,
+                total_tokens: 15,
+            },
+            cost: 0.5,
+            calls: vec![LlmCallRecord {
+                purpose: "answer".to_string(),
+                model: "yandexgpt".to_string(),
+                usage: TokenUsage {
+                    input_tokens: 10,
+                    completion_tokens: 5,
+                    total_tokens: 15,
+                },
+                cost: 0.5,
+                cached: false,
+            }],
+            latency_ms: 100,
+            parse_failures: 1,
+            intent: Some("question".to_string()),
+            topic: Some("cost".to_string()),
+            prompt_version: Some("1-0a1b2c3d".to_string()),
+            unverified_facts: Vec::new(),
+            error: answer.is_none().then(|| "timeout".to_string()),
+        }
+    }
+
+    #[test]
+    fn test_record_and_query() {
+        let storage = Storage::open_in_memory().unwrap();
+        storage.touch_chat(1, Some("user")).unwrap();
+        storage
+            .record_exchange(&exchange(1, "q1", Some("a1")))
+            .unwrap();
+        storage.record_exchange(&exchange(1, "q2", None)).unwrap();
+
+        let recent = storage.recent_exchanges(1, 10).unwrap();
+        assert_eq!(recent.len(), 2);
+        assert_eq!(recent[0].question, "q2");
+        assert_eq!(recent[1].relevant_fields, vec!["cost"]);
+        assert_eq!(recent[1].intent.as_deref(), Some("question"));
+        assert_eq!(recent[1].prompt_version.as_deref(), Some("1-0a1b2c3d"));
+
+        let errors = storage.recent_errors(10).unwrap();
+        assert_eq!(errors.len(), 1);
+        assert!(storage.recent_unverified(10).unwrap().is_empty());
+
+        let summary = storage.usage_summary(1).unwrap();
+        assert_eq!(summary.messages, 2);
+        assert_eq!(summary.errors, 1);
+        assert_eq!(summary.usage.total_tokens, 30);
+        assert_eq!(summary.parse_failures, 2);
+
+        let mut cached = exchange(1, "q1", Some("a1"));
+        cached.usage = TokenUsage::default();
+        cached.cost = 0.0;
+        cached.calls[0].cost = 0.0;
+        cached.calls[0].cached = true;
+        cached.unverified_facts = vec!["700 000 ₽".to_string()];
+        storage.record_exchange(&cached).unwrap();
+        let unverified = storage.recent_unverified(10).unwrap();
+        assert_eq!(unverified.len(), 1);
+        assert_eq!(unverified[0].unverified_facts, vec!["700 000 ₽"]);
+
+        let today = storage.spending(Period::Today).unwrap();
+        assert_eq!(today.questions, 3);
+        assert!((today.cost - 1.0).abs() < 1e-9);
+        assert_eq!(today.cache_hits, 1);
+        assert_eq!(today.saved_tokens, 15);
+    }
+
+    #[test]
+    fn test_dialogue_tails_skip_errors_and_limit_turns() {
+        let storage = Storage::open_in_memory().unwrap();
+        storage.touch_chat(1, None).unwrap();
+        storage.touch_chat(2, None).unwrap();
+        for i in 0..3 {
+            storage
+                .record_exchange(&exchange(1, &format!("q{i}"), Some("a")))
+                .unwrap();
+        }
+        storage
+            .record_exchange(&exchange(2, "failed", None))
+            .unwrap();
+        storage.reset_chat(2).unwrap();
+
+        let tails = storage.dialogue_tails(2).unwrap();
+        assert_eq!(tails.len(), 1);
+        assert_eq!(tails[0].chat_id, 1);
+        assert_eq!(tails[0].turns[0].question, "q1");
+        assert_eq!(tails[0].turns[1].question, "q2");
+    }
+
+    #[test]
+    fn test_quota_and_access_list() {
+        let storage = Storage::open_in_memory().unwrap();
+        storage.touch_chat(1, None).unwrap();
+        storage
+            .record_exchange(&exchange(1, "q", Some("a")))
+            .unwrap();
+        assert_eq!(storage.questions_today(1).unwrap(), 1);
+        assert_eq!(storage.questions_today(2).unwrap(), 0);
+
+        storage
+            .set_access(2, Some(AccessStatus::Banned), 1)
+            .unwrap();
+        storage
+            .set_access(3, Some(AccessStatus::Allowed), 1)
+            .unwrap();
+        storage.set_access(3, None, 1).unwrap();
+        assert_eq!(
+            storage.access_status(2).unwrap(),
+            Some(AccessStatus::Banned)
+        );
+        assert_eq!(storage.access_status(3).unwrap(), None);
+        assert_eq!(
+            storage.access_list().unwrap(),
+            vec![(2, AccessStatus::Banned)]
+        );
+    }
+}
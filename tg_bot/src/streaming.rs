@@ -0,0 +1,185 @@
+use std::time::Duration;
+
+use teloxide::prelude::*;
+use teloxide::types::MessageId;
+use teloxide::{ApiError, RequestError};
+use tokio::sync::watch;
+
+/// Максимальная длина сообщения в Telegram
+pub const TELEGRAM_MESSAGE_LIMIT: usize = 4096;
+
+/// Что показать вместо пустого ответа: Telegram не принимает сообщение без текста
+const EMPTY_ANSWER: &str = "Не удалось подготовить ответ, попробуйте переформулировать вопрос.";
+
+/// Сообщение-заглушка, которое редактируется по мере генерации ответа
+pub struct StreamingReply {
+    bot: Bot,
+    chat_id: ChatId,
+    message_id: MessageId,
+    editor: tokio::task::JoinHandle<String>,
+}
+
+impl StreamingReply {
+    /// Отправить заглушку и начать показывать частичный ответ из `partial`.
+    /// Правки идут не чаще одного раза в `interval`, чтобы не упереться в лимиты Telegram.
+    pub async fn start(
+        bot: Bot,
+        chat_id: ChatId,
+        placeholder: &str,
+        partial: watch::Receiver<String>,
+        interval: Duration,
+    ) -> Result<Self, RequestError> {
+        let message = bot.send_message(chat_id, placeholder).await?;
+        let editor = tokio::spawn(show_partial(
+            bot.clone(),
+            chat_id,
+            message.id,
+            partial,
+            interval,
+        ));
+        Ok(StreamingReply {
+            bot,
+            chat_id,
+            message_id: message.id,
+            editor,
+        })
+    }
+
+    /// Дождаться окончания стрима и показать итоговый ответ.
+    /// Отправитель `partial` к этому моменту должен быть уже закрыт.
+    pub async fn finish(self, answer: &str) -> Result<(), RequestError> {
+        let shown = self.editor.await.unwrap_or_default();
+
+        let mut parts = reply_parts(answer).into_iter();
+        let first = parts.next().unwrap_or_default();
+        if first != shown {
+            edit_text(&self.bot, self.chat_id, self.message_id, &first).await?;
+        }
+        for part in parts {
+            self.bot.send_message(self.chat_id, part).await?;
+        }
+        Ok(())
+    }
+}
+
+/// Возвращает последний показанный пользователю текст
+async fn show_partial(
+    bot: Bot,
+    chat_id: ChatId,
+    message_id: MessageId,
+    mut partial: watch::Receiver<String>,
+    interval: Duration,
+) -> String {
+    let mut shown = String::new();
+    while partial.changed().await.is_ok() {
+        let text = partial.borrow_and_update().clone();
+        let text = split_message(&text, TELEGRAM_MESSAGE_LIMIT)
+            .into_iter()
+            .next()
+            .unwrap_or_default();
+        if !text.trim().is_empty() && text != shown {
+            match edit_text(&bot, chat_id, message_id, &text).await {
+                Ok(()) => shown = text,
+                Err(err) => log::warn!("Failed to edit streaming message: {}", err),
+            }
+        }
+        tokio::time::sleep(interval).await;
+    }
+    shown
+}
+
+async fn edit_text(
+    bot: &Bot,
+    chat_id: ChatId,
+    message_id: MessageId,
+    text: &str,
+) -> Result<(), RequestError> {
+    match bot.edit_message_text(chat_id, message_id, text).await {
+        Ok(_) | Err(RequestError::Api(ApiError::MessageNotModified)) => Ok(()),
+        Err(err) => Err(err),
+    }
+}
+
+/// Части итогового ответа, для пустого ответа - сообщение `EMPTY_ANSWER`
+fn reply_parts(answer: &str) -> Vec<String> {
+    if answer.trim().is_empty() {
+        log::warn!("Model returned an empty answer");
+        return vec![EMPTY_ANSWER.to_string()];
+    }
+    split_message(answer, TELEGRAM_MESSAGE_LIMIT)
+}
+
+/// Разбить текст на части не длиннее `limit` символов, по возможности по переносам строк
+pub fn split_message(text: &str, limit: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut current = String::new();
+    let mut current_len = 0;
+    for line in text.split_inclusive('\n') {
+        let line_len = line.chars().count();
+        if current_len + line_len > limit && !current.is_empty() {
+            parts.push(std::mem::take(&mut current));
+            current_len = 0;
+        }
+        if line_len > limit {
+            let chars: Vec<char> = line.chars().collect();
+            for chunk in chars.chunks(limit) {
+                parts.push(chunk.iter().collect());
+            }
+            continue;
+        }
+        current.push_str(line);
+        current_len += line_len;
+    }
+    if !current.is_empty() {
+        parts.push(current);
+    }
+    parts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_message() {
+        assert_eq!(split_message("Короткий ответ", 20), vec!["Короткий ответ"]);
+        assert!(split_message("", 20).is_empty());
+
+        // Делится по переносам строк, абзац не разрывается
+        assert_eq!(
+            split_message("Первый абзац\n\nВторой абзац", 15),
+            vec!["Первый абзац\n\n", "Второй абзац"]
+        );
+        assert_eq!(
+            split_message("строка 1\nстрока 2\nстрока 3", 18),
+            vec!["строка 1\nстрока 2\n", "строка 3"]
+        );
+
+        // Строка длиннее лимита режется по символам
+        assert_eq!(
+            split_message("абв\nабвгдеёжз", 4),
+            vec!["абв\n", "абвг", "деёж", "з"]
+        );
+    }
+
+    #[test]
+    fn test_split_message_counts_chars_not_bytes() {
+        // 10 кириллических символов - 20 байт, но в лимит 10 укладываются
+        let text = "абвгдеёжзи";
+        assert_eq!(split_message(text, 10), vec![text]);
+
+        let text = "ё".repeat(25);
+        let parts = split_message(&text, 10);
+        assert_eq!(parts.len(), 3);
+        assert!(parts.iter().all(|part| part.chars().count() <= 10));
+        assert_eq!(parts.concat(), text);
+    }
+
+    #[test]
+    fn test_reply_parts_never_empty() {
+        // Иначе заглушка "Готовлю ответ…" так и осталась бы на экране
+        assert_eq!(reply_parts(""), vec![EMPTY_ANSWER]);
+        assert_eq!(reply_parts(" \n "), vec![EMPTY_ANSWER]);
+        assert_eq!(reply_parts("Ответ"), vec!["Ответ"]);
+    }
+}
@@ -0,0 +1,158 @@
+use serde_json::Value;
+
+/// Почему ответ модели не удалось разобрать как структурированный
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum OutputError {
+    #[error("reply contains no JSON array or object")]
+    NoJson,
+
+    #[error("invalid JSON: {0}")]
+    InvalidJson(String),
+
+    #[error("expected a JSON array of field names")]
+    NotAFieldList,
+
+    #[error("unknown fields: {}", unknown.join(", "))]
+    UnknownFields {
+        unknown: Vec<String>,
+        /// Поля из ответа, которые все-таки существуют
+        known: Vec<String>,
+    },
+}
+
+/// Найти в ответе модели первый JSON массив или объект. Модели часто
+/// оборачивают JSON в markdown блок ```json или добавляют пояснения
+/// до и после, поэтому сначала берется содержимое блока кода, если он есть.
+pub fn extract_json(reply: &str) -> Option<&str> {
+    let text = strip_code_fence(reply).unwrap_or(reply);
+    let start = text.find(['[', '{'])?;
+
+    let mut depth = 0usize;
+    let mut in_string = false;
+    let mut escaped = false;
+    for (offset, c) in text[start..].char_indices() {
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '[' | '{' => depth += 1,
+            ']' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&text[start..start + offset + 1]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+fn strip_code_fence(reply: &str) -> Option<&str> {
+    let (_, rest) = reply.split_once("```")?;
+    // Пропускаем язык блока: ```json
+    let rest = rest.split_once('\n').map_or(rest, |(_, body)| body);
+    let body = rest.split_once("```").map_or(rest, |(body, _)| body);
+    Some(body)
+}
+
+/// Разобрать список полей из ответа модели и проверить, что все поля
+/// есть в `known_fields`. Принимается массив строк или объект,
+/// в котором массив лежит в любом ключе (`{"fields": [...]}`).
+pub fn parse_field_list(reply: &str, known_fields: &[&str]) -> Result<Vec<String>, OutputError> {
+    let json = extract_json(reply).ok_or(OutputError::NoJson)?;
+    let value: Value =
+        serde_json::from_str(json).map_err(|e| OutputError::InvalidJson(e.to_string()))?;
+    let items = match value {
+        Value::Array(items) => items,
+        Value::Object(map) => map
+            .into_iter()
+            .find_map(|(_, value)| match value {
+                Value::Array(items) => Some(items),
+                _ => None,
+            })
+            .ok_or(OutputError::NotAFieldList)?,
+        _ => return Err(OutputError::NotAFieldList),
+    };
+
+    let mut known = Vec::new();
+    let mut unknown = Vec::new();
+    for item in items {
+        let Value::String(field) = item else {
+            return Err(OutputError::NotAFieldList);
+        };
+        let field = field.trim().to_string();
+        let list = if known_fields.contains(&field.as_str()) {
+            &mut known
+        } else {
+            &mut unknown
+        };
+        if !list.contains(&field) {
+            list.push(field);
+        }
+    }
+    if unknown.is_empty() {
+        Ok(known)
+    } else {
+        Err(OutputError::UnknownFields { unknown, known })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FIELDS: &[&str] = &["cost", "dormitory", "faq"];
+
+    #[test]
+    fn test_extract_json_from_fences_and_prose() {
+        assert_eq!(extract_json("```json\n[\"cost\"]\n```"), Some("[\"cost\"]"));
+        assert_eq!(
+            extract_json("Вот поля: [\"cost\", \"a]b\"] - это все"),
+            Some("[\"cost\", \"a]b\"]")
+        );
+        assert_eq!(
+            extract_json("{\"fields\": [\"faq\"]} и еще {}"),
+            Some("{\"fields\": [\"faq\"]}")
+        );
+        assert_eq!(extract_json("нет полей"), None);
+        assert_eq!(extract_json("[\"cost\""), None);
+    }
+
+    #[test]
+    fn test_parse_field_list() {
+        assert_eq!(
+            parse_field_list(
+                "```json\n[\"cost\", \" dormitory \", \"cost\"]\n```",
+                FIELDS
+            ),
+            Ok(vec!["cost".to_string(), "dormitory".to_string()])
+        );
+        assert_eq!(
+            parse_field_list("{\"fields\": [\"faq\"]}", FIELDS),
+            Ok(vec!["faq".to_string()])
+        );
+        assert_eq!(
+            parse_field_list("[\"cost\", \"price\"]", FIELDS),
+            Err(OutputError::UnknownFields {
+                unknown: vec!["price".to_string()],
+                known: vec!["cost".to_string()],
+            })
+        );
+        assert_eq!(
+            parse_field_list("[1, 2]", FIELDS),
+            Err(OutputError::NotAFieldList)
+        );
+        assert_eq!(
+            parse_field_list("не знаю", FIELDS),
+            Err(OutputError::NoJson)
+        );
+    }
+}
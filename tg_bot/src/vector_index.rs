@@ -0,0 +1,355 @@
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::chunker::Chunk;
+use crate::config::{Config, EmbeddingsConfig};
+use crate::embeddings::{self, EmbeddingKind, EmbeddingProvider};
+use crate::llm_error::LlmError;
+use crate::llm_provider::TokenUsage;
+use crate::programs::ProgramRegistry;
+use crate::resilience::ResilientProvider;
+
+/// Сколько раз повторять запрос вектора при временной ошибке во время построения индекса
+const EMBED_ATTEMPTS: u32 = 3;
+
+/// Фрагмент программы и его вектор
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VectorEntry {
+    #[serde(flatten)]
+    pub chunk: Chunk,
+    pub vector: Vec<f32>,
+}
+
+/// Векторы фрагментов всех программ. Строится бинарником `build_index`
+/// или ботом при старте и хранится в json, поиск - перебором по косинусу,
+/// фрагментов несколько сотен.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct VectorIndex {
+    /// Модель, которой построены векторы
+    pub model: String,
+    entries: Vec<VectorEntry>,
+}
+
+/// Найденный по смыслу фрагмент
+#[derive(Debug, Clone, Copy)]
+pub struct SemanticHit<'a> {
+    pub chunk: &'a Chunk,
+    /// Косинусная близость к вопросу
+    pub score: f32,
+}
+
+impl VectorIndex {
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path)
+            .map_err(|e| anyhow::anyhow!("Failed to read {}: {}", path.display(), e))?;
+        serde_json::from_str(&json)
+            .map_err(|e| anyhow::anyhow!("Invalid vector index {}: {}", path.display(), e))
+    }
+
+    /// Записать во временный файл и переименовать, чтобы бот не прочитал недописанный индекс
+    pub fn save(&self, path: impl AsRef<Path>) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+            fs::create_dir_all(dir)?;
+        }
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_string(self)?)?;
+        fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Построен ли индекс моделью `model` ровно по этим фрагментам.
+    /// Если парсер обновил данные программы, фрагменты меняются и индекс нужно перестроить.
+    pub fn is_current(&self, model: &str, chunks: &[Chunk]) -> bool {
+        self.model == model
+            && self.entries.len() == chunks.len()
+            && self
+                .entries
+                .iter()
+                .zip(chunks)
+                .all(|(entry, chunk)| entry.chunk == *chunk)
+    }
+
+    /// Получить векторы фрагментов. Векторы фрагментов, текст которых не изменился
+    /// с `previous`, берутся оттуда, если он построен той же моделью.
+    pub async fn build(
+        embedder: &dyn EmbeddingProvider,
+        chunks: Vec<Chunk>,
+        previous: Option<&VectorIndex>,
+    ) -> Result<VectorIndex, LlmError> {
+        let previous = previous.filter(|index| index.model == embedder.model());
+        let mut entries = Vec::with_capacity(chunks.len());
+        let mut embedded = 0;
+        let mut tokens = 0;
+        for chunk in chunks {
+            let reused = previous.and_then(|index| {
+                index
+                    .entries
+                    .iter()
+                    .find(|entry| entry.chunk.render() == chunk.render())
+                    .map(|entry| entry.vector.clone())
+            });
+            let vector = match reused {
+                Some(vector) => vector,
+                None => {
+                    let embedding = embed_document(embedder, &chunk.render()).await?;
+                    embedded += 1;
+                    tokens += embedding.tokens;
+                    embedding.vector
+                }
+            };
+            entries.push(VectorEntry { chunk, vector });
+        }
+        log::info!(
+            "Built vector index with {} chunks: {} embedded ({} tokens), {} reused",
+            entries.len(),
+            embedded,
+            tokens,
+            entries.len() - embedded
+        );
+        Ok(VectorIndex {
+            model: embedder.model().to_string(),
+            entries,
+        })
+    }
+
+    /// До `limit` фрагментов, подходящих под `filter`, с близостью не ниже `min_score`
+    pub fn search(
+        &self,
+        query: &[f32],
+        limit: usize,
+        min_score: f32,
+        filter: impl Fn(&Chunk) -> bool,
+    ) -> Vec<SemanticHit<'_>> {
+        let mut hits: Vec<SemanticHit> = self
+            .entries
+            .iter()
+            .filter(|entry| filter(&entry.chunk))
+            .map(|entry| SemanticHit {
+                chunk: &entry.chunk,
+                score: embeddings::cosine(query, &entry.vector),
+            })
+            .filter(|hit| hit.score >= min_score)
+            .collect();
+        // sort_by стабильная: при равной близости порядок как в данных
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(limit);
+        hits
+    }
+}
+
+async fn embed_document(
+    embedder: &dyn EmbeddingProvider,
+    text: &str,
+) -> Result<embeddings::Embedding, LlmError> {
+    let mut attempt = 1;
+    loop {
+        match embedder.embed(text, EmbeddingKind::Document).await {
+            Err(err) if err.is_retryable() && attempt < EMBED_ATTEMPTS => {
+                let delay = match &err {
+                    LlmError::RateLimited {
+                        retry_after: Some(retry_after),
+                        ..
+                    } => *retry_after,
+                    _ => Duration::from_secs(attempt as u64),
+                };
+                log::warn!(
+                    "Embedding failed (attempt {}), retrying in {:?}: {}",
+                    attempt,
+                    delay,
+                    err
+                );
+                tokio::time::sleep(delay).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Семантический поиск по индексу: вопрос превращается в вектор тем же провайдером
+pub struct SemanticSearch {
+    embedder: Arc<dyn EmbeddingProvider>,
+    index: VectorIndex,
+}
+
+impl SemanticSearch {
+    pub fn new(embedder: Arc<dyn EmbeddingProvider>, index: VectorIndex) -> Self {
+        SemanticSearch { embedder, index }
+    }
+
+    /// Семантический поиск по настройкам `[embeddings]`. Ошибки не мешают
+    /// отвечать на вопросы: поиск выключается, а причина пишется в лог.
+    /// Векторы вопросов запрашиваются с повторами и circuit breaker из `[resilience]`.
+    pub async fn from_config(config: &Config, data: &ProgramRegistry) -> Option<Self> {
+        let embedder =
+            match embeddings::embeddings_from_config(&config.embeddings, &config.llm, &config.http)
+            {
+                Ok(Some(embedder)) => embedder,
+                Ok(None) => return None,
+                Err(err) => {
+                    log::error!("Semantic search is disabled: {}", err);
+                    return None;
+                }
+            };
+        log::info!(
+            "Using embeddings provider: {} ({})",
+            embedder.name(),
+            embedder.model()
+        );
+        let search = SemanticSearch::open(&config.embeddings, embedder, data)
+            .await
+            .inspect_err(|err| log::error!("Semantic search is disabled: {}", err))
+            .ok()
+            .flatten()?;
+        let resilience = config.resilience.clone();
+        Some(search.map_embedder(|embedder| Arc::new(ResilientProvider::new(embedder, resilience))))
+    }
+
+    /// Обернуть провайдер, которым считаются векторы вопросов,
+    /// например в `BudgetGuard`. Индекс при этом не перестраивается.
+    pub fn map_embedder(
+        self,
+        wrap: impl FnOnce(Arc<dyn EmbeddingProvider>) -> Arc<dyn EmbeddingProvider>,
+    ) -> Self {
+        SemanticSearch {
+            embedder: wrap(self.embedder),
+            index: self.index,
+        }
+    }
+
+    /// Загрузить индекс из `config.index_path`. Если данные программ изменились
+    /// (например, после `parse_html`), индекс перестраивается и сохраняется,
+    /// а при `rebuild_on_start = false` семантический поиск выключается до запуска `build_index`.
+    pub async fn open(
+        config: &EmbeddingsConfig,
+        embedder: Arc<dyn EmbeddingProvider>,
+        data: &ProgramRegistry,
+    ) -> anyhow::Result<Option<Self>> {
+        let chunks = data.index().chunks();
+        let previous = VectorIndex::load(&config.index_path)
+            .inspect_err(|e| log::warn!("No usable vector index: {}", e))
+            .ok();
+        if let Some(index) = previous
+            .as_ref()
+            .filter(|index| index.is_current(embedder.model(), chunks))
+        {
+            log::info!(
+                "Loaded vector index with {} chunks from {}",
+                index.len(),
+                config.index_path.display()
+            );
+            return Ok(Some(SemanticSearch::new(embedder, index.clone())));
+        }
+        if !config.rebuild_on_start {
+            log::warn!(
+                "Vector index {} is missing or outdated, semantic search is disabled until build_index is run",
+                config.index_path.display()
+            );
+            return Ok(None);
+        }
+        log::info!(
+            "Program data changed, rebuilding vector index {}",
+            config.index_path.display()
+        );
+        let index =
+            VectorIndex::build(embedder.as_ref(), chunks.to_vec(), previous.as_ref()).await?;
+        index.save(&config.index_path)?;
+        Ok(Some(SemanticSearch::new(embedder, index)))
+    }
+
+    pub fn index(&self) -> &VectorIndex {
+        &self.index
+    }
+
+    /// Фрагменты, близкие к вопросу по смыслу, и сколько токенов стоил вектор вопроса
+    pub async fn search(
+        &self,
+        query: &str,
+        limit: usize,
+        min_score: f32,
+        filter: impl Fn(&Chunk) -> bool,
+    ) -> Result<(Vec<SemanticHit<'_>>, TokenUsage), LlmError> {
+        let embedding = self.embedder.embed(query, EmbeddingKind::Query).await?;
+        let hits = self
+            .index
+            .search(&embedding.vector, limit, min_score, filter);
+        let usage = TokenUsage {
+            input_tokens: embedding.tokens,
+            completion_tokens: 0,
+            total_tokens: embedding.tokens,
+        };
+        Ok((hits, usage))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(text: &str, vector: &[f32]) -> VectorEntry {
+        VectorEntry {
+            chunk: Chunk {
+                id: format!("ai/{text}"),
+                program: "ai".to_string(),
+                field: "faq".to_string(),
+                index: None,
+                label: "Частый вопрос".to_string(),
+                text: text.to_string(),
+                url: "https://abit.itmo.ru/program/master/ai#faq".to_string(),
+                section: "Часто задаваемые вопросы".to_string(),
+            },
+            vector: vector.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_search_and_staleness() {
+        let index = VectorIndex {
+            model: "doc".to_string(),
+            entries: vec![
+                entry("Общежитие", &[1.0, 0.0, 0.0]),
+                entry("Вечерние занятия", &[0.0, 1.0, 0.2]),
+                entry("Стоимость", &[0.0, 0.2, 1.0]),
+            ],
+        };
+        let hits = index.search(&[0.1, 1.0, 0.0], 2, 0.0, |_| true);
+        let texts: Vec<_> = hits.iter().map(|hit| hit.chunk.text.as_str()).collect();
+        assert_eq!(texts, ["Вечерние занятия", "Стоимость"]);
+        assert!(index.search(&[0.1, 1.0, 0.0], 5, 0.9, |_| true).len() == 1);
+        assert!(
+            index
+                .search(&[1.0, 0.0, 0.0], 5, 0.0, |p| p.text != "Общежитие")
+                .iter()
+                .all(|hit| hit.chunk.text != "Общежитие")
+        );
+
+        let chunks: Vec<Chunk> = index.entries.iter().map(|e| e.chunk.clone()).collect();
+        assert!(index.is_current("doc", &chunks));
+        assert!(!index.is_current("other", &chunks));
+        let mut changed = chunks.clone();
+        changed[2].text = "Стоимость обучения".to_string();
+        assert!(!index.is_current("doc", &changed));
+        assert!(!index.is_current("doc", &chunks[..2]));
+
+        let path = std::env::temp_dir().join(format!("vector_index_{}.json", fastrand::u64(..)));
+        index.save(&path).unwrap();
+        let loaded = VectorIndex::load(&path).unwrap();
+        assert!(loaded.is_current("doc", &chunks));
+        assert_eq!(loaded.entries[1].vector, index.entries[1].vector);
+        fs::remove_file(path).unwrap();
+    }
+}
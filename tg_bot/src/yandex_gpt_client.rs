@@ -1,89 +1,218 @@
+use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
+use tokio::sync::watch;
+
+use crate::llm_error::LlmError;
+use crate::llm_provider::{
+    ChatCompletion, ChatMessage, ChatRole, GenerationOptions, LlmProvider, TokenUsage,
+};
 
 // Структура для клиента Yandex SpeechKit
 #[derive(Debug, Clone)]
 pub struct YandexGPTClient {
+    http: reqwest::Client,
     api_key: String,
     base_url: String,
     folder_id: String,
+    model: String,
+    /// Версия модели в URI, `None` - версия по умолчанию
+    model_version: Option<String>,
 }
 
 impl YandexGPTClient {
-    pub fn new(api_key: String, base_url: String, folder_id: String) -> Self {
+    pub fn new(
+        http: reqwest::Client,
+        api_key: String,
+        base_url: String,
+        folder_id: String,
+        model: String,
+        model_version: Option<String>,
+    ) -> Self {
         YandexGPTClient {
+            http,
             api_key,
             base_url,
             folder_id,
+            model,
+            model_version,
         }
     }
 
-    pub async fn get_answer(&self, system_prompt: &str, user_text: &str) -> anyhow::Result<String> {
-        let client = reqwest::Client::new();
-        let modelUri = format!("gpt://{}/yandexgpt", self.folder_id);
+    fn model_uri(&self) -> String {
+        match &self.model_version {
+            Some(version) => format!("gpt://{}/{}/{}", self.folder_id, self.model, version),
+            None => format!("gpt://{}/{}", self.folder_id, self.model),
+        }
+    }
 
+    fn request(
+        &self,
+        messages: &[ChatMessage],
+        options: &GenerationOptions,
+        stream: bool,
+    ) -> reqwest::RequestBuilder {
         // Создаем JSON запрос
         let request_body = YandexGPTRequest {
-            modelUri: modelUri,
-            completionOptions: CompletionOptions {
-                stream: false,
-                temperature: 0.0,
-                maxTokens: "4000".to_string(),
+            model_uri: self.model_uri(),
+            completion_options: CompletionOptions {
+                stream,
+                temperature: options.temperature,
+                max_tokens: options.max_tokens.to_string(),
             },
-            messages: vec![
-                Message {
-                    role: Role::system,
-                    text: system_prompt.to_string(),
-                },
-                Message {
-                    role: Role::user,
-                    text: user_text.to_string(),
-                },
-            ],
+            messages: messages.iter().map(Message::from).collect(),
         };
 
-        let response = client
+        self.http
             .post(&self.base_url)
             .header("Authorization", format!("Api-Key {}", self.api_key))
-            .json(&serde_json::json!(request_body))
-            .send()
-            .await?;
-
-        if response.status().is_success() {
-            let summary: YandexGPTResponse = response.json().await?;
-            let text = summary.result.alternatives[0].message.text.clone();
-            println!("request: {}", user_text);
-            println!("system prompt: {}", system_prompt);
-            println!("answer: {}", text);
-            Ok(text)
-        } else {
-            Err(anyhow::anyhow!("Failed to get summary"))
+            .json(&request_body)
+    }
+}
+
+#[async_trait]
+impl LlmProvider for YandexGPTClient {
+    fn name(&self) -> &str {
+        "yandex"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    async fn complete(
+        &self,
+        messages: &[ChatMessage],
+        options: &GenerationOptions,
+    ) -> Result<ChatCompletion, LlmError> {
+        let response = self.request(messages, options, false).send().await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.text().await?;
+        if !status.is_success() {
+            return Err(LlmError::from_status(status, &headers, body));
         }
+
+        let completion = parse_response(&body)?;
+        log::debug!("answer: {}", completion.text);
+        Ok(completion)
+    }
+
+    async fn complete_stream(
+        &self,
+        messages: &[ChatMessage],
+        options: &GenerationOptions,
+        partial: &watch::Sender<String>,
+    ) -> Result<ChatCompletion, LlmError> {
+        let mut response = self.request(messages, options, true).send().await?;
+        let status = response.status();
+        if !status.is_success() {
+            let headers = response.headers().clone();
+            let body = response.text().await?;
+            return Err(LlmError::from_status(status, &headers, body));
+        }
+
+        // В режиме stream API отдает JSON объекты по одному на строку,
+        // в каждом из них накопленный к этому моменту текст ответа
+        let mut buffer: Vec<u8> = Vec::new();
+        let mut last: Option<ChatCompletion> = None;
+        while let Some(bytes) = response.chunk().await? {
+            buffer.extend_from_slice(&bytes);
+            while let Some(newline) = buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = buffer.drain(..=newline).collect();
+                if let Some(chunk) = parse_stream_line(&line)? {
+                    partial.send_replace(chunk.text.clone());
+                    last = Some(chunk);
+                }
+            }
+        }
+        if let Some(chunk) = parse_stream_line(&buffer)? {
+            partial.send_replace(chunk.text.clone());
+            last = Some(chunk);
+        }
+
+        let completion = last.ok_or_else(|| LlmError::EmptyAlternatives {
+            body: String::new(),
+        })?;
+        log::debug!("answer: {}", completion.text);
+        Ok(completion)
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+fn parse_stream_line(line: &[u8]) -> Result<Option<ChatCompletion>, LlmError> {
+    let line = String::from_utf8_lossy(line);
+    let line = line.trim();
+    if line.is_empty() {
+        return Ok(None);
+    }
+    parse_response(line).map(Some)
+}
+
+fn parse_response(body: &str) -> Result<ChatCompletion, LlmError> {
+    let response: YandexGPTResponse =
+        serde_json::from_str(body).map_err(|err| LlmError::malformed(err, body))?;
+    let alternative = response
+        .result
+        .alternatives
+        .into_iter()
+        .next()
+        .ok_or_else(|| LlmError::EmptyAlternatives {
+            body: body.to_string(),
+        })?;
+    if alternative.status == "ALTERNATIVE_STATUS_CONTENT_FILTER" {
+        return Err(LlmError::ContentFiltered {
+            status: alternative.status,
+            body: body.to_string(),
+        });
+    }
+
+    Ok(ChatCompletion {
+        text: alternative.message.text,
+        usage: TokenUsage::from(&response.result.usage),
+        model: response.result.model_version,
+    })
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct CompletionOptions {
     pub stream: bool,
     pub temperature: f32,
-    pub maxTokens: String,
+    pub max_tokens: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Message {
     pub role: Role,
     pub text: String,
 }
-#[derive(Debug, Serialize, Deserialize)]
-enum Role {
-    system,
-    user,
-    assistant,
+
+impl From<&ChatMessage> for Message {
+    fn from(message: &ChatMessage) -> Self {
+        let role = match message.role {
+            ChatRole::System => Role::System,
+            ChatRole::User => Role::User,
+            ChatRole::Assistant => Role::Assistant,
+        };
+        Message {
+            role,
+            text: message.text.clone(),
+        }
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct YandexGPTRequest {
-    pub modelUri: String,
-    pub completionOptions: CompletionOptions,
+    pub model_uri: String,
+    pub completion_options: CompletionOptions,
     pub messages: Vec<Message>,
 }
 
@@ -93,10 +222,11 @@ pub struct YandexGPTResponse {
 }
 
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct ResultData {
     pub alternatives: Vec<Alternative>,
     pub usage: Usage,
-    pub modelVersion: String,
+    pub model_version: String,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -105,9 +235,21 @@ pub struct Alternative {
     pub status: String,
 }
 
+// Yandex отдает счетчики токенов строками (int64 в JSON)
 #[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct Usage {
-    pub inputTextTokens: String,
-    pub completionTokens: String,
-    pub totalTokens: String,
+    pub input_text_tokens: String,
+    pub completion_tokens: String,
+    pub total_tokens: String,
+}
+
+impl From<&Usage> for TokenUsage {
+    fn from(usage: &Usage) -> Self {
+        TokenUsage {
+            input_tokens: usage.input_text_tokens.parse().unwrap_or(0),
+            completion_tokens: usage.completion_tokens.parse().unwrap_or(0),
+            total_tokens: usage.total_tokens.parse().unwrap_or(0),
+        }
+    }
 }
@@ -0,0 +1,380 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::sync::watch;
+
+use tg_bot::answer_cache::{AnswerCache, CacheConfig};
+use tg_bot::config::Config;
+use tg_bot::embeddings::{EmbeddingProvider, YandexEmbeddings};
+use tg_bot::intent::IntentClassifier;
+use tg_bot::llm_error::LlmError;
+use tg_bot::llm_provider::{ChatMessage, GenerationOptions, LlmProvider};
+use tg_bot::mock_llm::{MockReply, MockYandexGpt};
+use tg_bot::pipeline::{AnswerContext, AnswerTrace, call_model, get_answer_from_llm};
+use tg_bot::programs::ProgramRegistry;
+use tg_bot::prompts::PromptSet;
+use tg_bot::resilience::{ResilienceConfig, ResilientProvider};
+use tg_bot::vector_index::{SemanticSearch, VectorIndex};
+use tg_bot::yandex_gpt_client::YandexGPTClient;
+
+fn client(server: &MockYandexGpt) -> Arc<dyn LlmProvider> {
+    Arc::new(YandexGPTClient::new(
+        reqwest::Client::new(),
+        "test-key".to_string(),
+        server.url(),
+        "folder".to_string(),
+        "yandexgpt".to_string(),
+        Some("rc".to_string()),
+    ))
+}
+
+fn resilient(server: &MockYandexGpt, deadline: Duration) -> ResilientProvider {
+    ResilientProvider::new(
+        client(server),
+        ResilienceConfig {
+            max_retries: 2,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(50),
+            deadline,
+            ..ResilienceConfig::default()
+        },
+    )
+}
+
+fn question() -> Vec<ChatMessage> {
+    vec![
+        ChatMessage::system("Отвечай коротко"),
+        ChatMessage::user("Сколько стоит обучение?"),
+    ]
+}
+
+#[tokio::test]
+async fn test_complete_sends_request_and_parses_usage() {
+    let server = MockYandexGpt::start().await.unwrap();
+    server.push(MockReply::text("599 000 рублей в год").with_usage(12, 4));
+
+    let completion = client(&server)
+        .complete(&question(), &GenerationOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(completion.text, "599 000 рублей в год");
+    assert_eq!(completion.usage.input_tokens, 12);
+    assert_eq!(completion.usage.completion_tokens, 4);
+    assert_eq!(completion.model, "mock");
+
+    let requests = server.requests();
+    assert_eq!(requests.len(), 1);
+    assert_eq!(
+        requests[0].authorization.as_deref(),
+        Some("Api-Key test-key")
+    );
+    assert_eq!(requests[0].body.model_uri, "gpt://folder/yandexgpt/rc");
+    assert!(!requests[0].body.completion_options.stream);
+    assert_eq!(requests[0].last_user_text(), "Сколько стоит обучение?");
+
+    // Без сценария отвечает responder по умолчанию
+    let completion = client(&server)
+        .complete(&question(), &GenerationOptions::default())
+        .await
+        .unwrap();
+    assert!(completion.text.contains("Сколько стоит обучение?"));
+}
+
+#[tokio::test]
+async fn test_stream_delivers_chunks() {
+    let server = MockYandexGpt::start().await.unwrap();
+    server.push(
+        MockReply::chunks(&["Стоимость ", "обучения ", "599 000"])
+            .with_chunk_delay(Duration::from_millis(30))
+            .with_usage(10, 3),
+    );
+
+    let (partial, mut updates) = watch::channel(String::new());
+    let collector = tokio::spawn(async move {
+        let mut seen = Vec::new();
+        while updates.changed().await.is_ok() {
+            seen.push(updates.borrow_and_update().clone());
+        }
+        seen
+    });
+    let completion = client(&server)
+        .complete_stream(&question(), &GenerationOptions::default(), &partial)
+        .await
+        .unwrap();
+    drop(partial);
+
+    assert_eq!(completion.text, "Стоимость обучения 599 000");
+    assert_eq!(completion.usage.completion_tokens, 3);
+    assert!(server.requests()[0].body.completion_options.stream);
+    let seen = collector.await.unwrap();
+    assert_eq!(
+        seen,
+        vec![
+            "Стоимость ",
+            "Стоимость обучения ",
+            "Стоимость обучения 599 000"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_errors_map_to_llm_error() {
+    let server = MockYandexGpt::start().await.unwrap();
+    server.push(MockReply::error(401, "bad key"));
+    server.push(MockReply::error(429, "slow down").with_header("Retry-After", "7"));
+    server.push(MockReply::content_filter());
+    server.push(MockReply::malformed("not json"));
+
+    let llm = client(&server);
+    let options = GenerationOptions::default();
+    let err = llm.complete(&question(), &options).await.unwrap_err();
+    assert!(matches!(err, LlmError::Auth { .. }), "{err}");
+    match llm.complete(&question(), &options).await.unwrap_err() {
+        LlmError::RateLimited { retry_after, .. } => {
+            assert_eq!(retry_after, Some(Duration::from_secs(7)))
+        }
+        err => panic!("unexpected error: {err}"),
+    }
+    let err = llm.complete(&question(), &options).await.unwrap_err();
+    assert!(matches!(err, LlmError::ContentFiltered { .. }), "{err}");
+    let names = ProgramRegistry::load("data/programs").unwrap().names();
+    assert!(
+        err.user_message(&names)
+            .ends_with(&format!("о магистратурах {names}."))
+    );
+    let err = llm.complete(&question(), &options).await.unwrap_err();
+    assert!(matches!(err, LlmError::MalformedResponse { .. }), "{err}");
+}
+
+#[tokio::test]
+async fn test_resilient_provider_retries_and_respects_deadline() {
+    let server = MockYandexGpt::start().await.unwrap();
+    server.push(MockReply::error(503, "unavailable"));
+    server.push(MockReply::error(500, "internal"));
+    server.push(MockReply::text("Ответ"));
+
+    let llm = resilient(&server, Duration::from_secs(5));
+    let completion = llm
+        .complete(&question(), &GenerationOptions::default())
+        .await
+        .unwrap();
+    assert_eq!(completion.text, "Ответ");
+    assert_eq!(server.requests().len(), 3);
+
+    // Ошибки авторизации не повторяются
+    server.push(MockReply::error(403, "forbidden"));
+    let err = llm
+        .complete(&question(), &GenerationOptions::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, LlmError::Auth { .. }), "{err}");
+    assert_eq!(server.requests().len(), 4);
+
+    let server = MockYandexGpt::start_with(|_| {
+        MockReply::text("Поздно").with_latency(Duration::from_millis(500))
+    })
+    .await
+    .unwrap();
+    let err = resilient(&server, Duration::from_millis(100))
+        .complete(&question(), &GenerationOptions::default())
+        .await
+        .unwrap_err();
+    assert!(matches!(err, LlmError::DeadlineExceeded(_)), "{err}");
+}
+
+#[tokio::test]
+async fn test_pipeline_routes_question_and_falls_back_on_errors() {
+    let server = MockYandexGpt::start().await.unwrap();
+    let mut config = Config::default();
+    config.verification.action = "regenerate".to_string();
+    let data = ProgramRegistry::load(&config.data.programs_dir).unwrap();
+    let prompts = PromptSet::load(&config.prompts.dir).unwrap();
+    let cache = AnswerCache::new(CacheConfig {
+        ttl: Duration::ZERO,
+        ..CacheConfig::default()
+    });
+    let classifier = IntentClassifier::new(data.aliases());
+    let llm = resilient(&server, Duration::from_secs(5));
+    let context = AnswerContext {
+        data: &data,
+        llm: &llm,
+        cache: &cache,
+        classifier: &classifier,
+        config: &config,
+        prompts: &prompts,
+        semantic: None,
+    };
+    let ask = |question: &'static str| {
+        let context = &context;
+        async move {
+            let (partial, _) = watch::channel(String::new());
+            let mut trace = AnswerTrace::default();
+            let answer = get_answer_from_llm(question, &[], context, &partial, &mut trace).await;
+            (answer, trace)
+        }
+    };
+
+    server.push(MockReply::text(r#"["cost"]"#));
+    server.push(MockReply::chunks(&["Обучение ", "стоит 599 000 ₽ [1]"]));
+    let (answer, trace) = ask("Сколько стоит обучение на AI Product?").await;
+    // Ссылка в ответе раскрывается в список источников со ссылкой на страницу программы
+    assert_eq!(
+        answer,
+        "Обучение стоит 599 000 ₽ [1]\n\nИсточники:\n\
+         [1] AI Product · Основные сведения: https://abit.itmo.ru/program/master/ai_product"
+    );
+    assert_eq!(trace.program.as_deref(), Some("ai_product"));
+    assert_eq!(trace.relevant_fields, vec!["cost"]);
+    assert_eq!(trace.error, None);
+    let requests = server.requests();
+    assert_eq!(requests.len(), 2);
+    // Фрагменты выбранных полей попадают в системный промпт ответа текстом, а не json
+    let system = &requests[1].body.messages[0].text;
+    assert!(
+        system.contains("[1] Стоимость обучения: 599 000 ₽"),
+        "{system}"
+    );
+    assert!(!system.contains("\"cost\""), "{system}");
+    assert_eq!(
+        requests[1].last_user_text(),
+        "Сколько стоит обучение на AI Product?"
+    );
+
+    // Все попытки неудачны: пользователь получает ответ без модели.
+    // Поля нашлись поиском, так что запросы только на ответ: первый и два повтора
+    for _ in 0..3 {
+        server.push(MockReply::error(500, "internal"));
+    }
+    let (answer, trace) = ask("Какие курсы по машинному обучению на AI?").await;
+    assert!(trace.error.is_some());
+    assert_eq!(server.requests().len(), 5);
+    assert!(answer.contains("Релевантные курсы"), "{answer}");
+
+    // Вопрос целиком нашелся в FAQ: поля у модели не спрашиваем
+    let before = server.requests().len();
+    server.push(MockReply::text("Можно"));
+    let (answer, trace) = ask("Можно ли поступить на AI без профильного образования?").await;
+    assert_eq!(answer, "Можно");
+    assert_eq!(server.requests().len(), before + 1);
+    assert!(trace.relevant_fields.contains(&"faq".to_string()));
+    assert!(
+        trace
+            .calls
+            .iter()
+            .all(|(purpose, _)| *purpose != "field_selection")
+    );
+
+    // Выдуманная стоимость: модель отвечает заново, подсказка перечисляет значения
+    let before = server.requests().len();
+    server.push(MockReply::text(r#"["cost"]"#));
+    server.push(MockReply::text("Обучение стоит 700 000 ₽ [1]"));
+    server.push(MockReply::text("Обучение стоит 599 000 ₽ [1]"));
+    let (answer, trace) = ask("Какая стоимость обучения на AI Product?").await;
+    assert!(
+        answer.starts_with("Обучение стоит 599 000 ₽ [1]\n\nИсточники:"),
+        "{answer}"
+    );
+    assert_eq!(trace.unverified_facts, vec!["700 000 ₽"]);
+    assert!(
+        trace
+            .calls
+            .iter()
+            .any(|(purpose, _)| *purpose == "fact_check")
+    );
+    let requests = server.requests();
+    assert_eq!(requests.len(), before + 3);
+    assert!(requests[before + 2].last_user_text().contains("700 000 ₽"));
+}
+
+#[tokio::test]
+async fn test_vector_index_rebuilds_changed_chunks_and_feeds_prompt() {
+    let server = MockYandexGpt::start().await.unwrap();
+    let embedder = Arc::new(YandexEmbeddings::new(
+        reqwest::Client::new(),
+        "test-key".to_string(),
+        server.embedding_url(),
+        "folder".to_string(),
+        "text-search-doc".to_string(),
+        "text-search-query".to_string(),
+    ));
+    let mut config = Config::default();
+    config.embeddings.min_score = 0.0;
+    config.embeddings.top_k = 1;
+    let data = ProgramRegistry::load(&config.data.programs_dir).unwrap();
+    let chunks = data.index().chunks().to_vec();
+
+    let index = VectorIndex::build(embedder.as_ref(), chunks.clone(), None)
+        .await
+        .unwrap();
+    assert_eq!(index.model, "text-search-doc");
+    assert!(index.is_current(embedder.model(), &chunks));
+    let requests = server.embedding_requests();
+    assert_eq!(requests.len(), chunks.len());
+    assert_eq!(requests[0].model_uri, "emb://folder/text-search-doc/latest");
+
+    // После нового запуска парсера векторы нужны только измененным фрагментам
+    let mut changed = chunks.clone();
+    changed[0].text.push_str(" (обновлено)");
+    assert!(!index.is_current(embedder.model(), &changed));
+    let index = VectorIndex::build(embedder.as_ref(), changed.clone(), Some(&index))
+        .await
+        .unwrap();
+    assert_eq!(server.embedding_requests().len(), chunks.len() + 1);
+    assert!(index.is_current(embedder.model(), &changed));
+
+    let semantic = SemanticSearch::new(embedder, index);
+    let question = "Сколько стоит обучение на AI Product?";
+    let (hits, _) = semantic
+        .search(question, 1, 0.0, |chunk| chunk.program == "ai_product")
+        .await
+        .unwrap();
+    let expected = hits[0].chunk.text.clone();
+    assert_eq!(
+        server.embedding_requests().last().unwrap().model_uri,
+        "emb://folder/text-search-query/latest"
+    );
+
+    let prompts = PromptSet::load(&config.prompts.dir).unwrap();
+    let cache = AnswerCache::new(CacheConfig {
+        ttl: Duration::ZERO,
+        ..CacheConfig::default()
+    });
+    let classifier = IntentClassifier::new(data.aliases());
+    let llm = client(&server);
+    let context = AnswerContext {
+        data: &data,
+        llm: llm.as_ref(),
+        cache: &cache,
+        classifier: &classifier,
+        config: &config,
+        prompts: &prompts,
+        semantic: Some(&semantic),
+    };
+    server.push(MockReply::text(r#"["cost"]"#));
+    server.push(MockReply::text("599 000 ₽"));
+    let (partial, _) = watch::channel(String::new());
+    let mut trace = AnswerTrace::default();
+    let answer = get_answer_from_llm(question, &[], &context, &partial, &mut trace).await;
+    assert_eq!(answer, "599 000 ₽");
+    // Близкий по смыслу фрагмент попадает в промпт вместе с выбранными полями
+    let requests = server.requests();
+    assert!(requests[1].body.messages[0].text.contains(&expected));
+    assert_eq!(
+        trace.relevant_fields.len(),
+        2,
+        "{:?}",
+        trace.relevant_fields
+    );
+    // Вектор вопроса учитывается в расходах вместе с вызовами LLM
+    let embedding = trace
+        .calls
+        .iter()
+        .find(|(purpose, _)| *purpose == "embedding")
+        .map(|(_, usage)| *usage)
+        .unwrap();
+    assert!(embedding.input_tokens > 0);
+    assert_eq!(
+        call_model("embedding", llm.as_ref(), &config),
+        "text-search-query"
+    );
+}